@@ -0,0 +1,47 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use num::complex::Complex;
+
+use fractatoe::fractals::escape_time::{escape_time_histogram, iterate_until_escape};
+use fractatoe::fractals::histogram::HistogramBuilder;
+use fractatoe::fractals::mandelbrot::Mandelbrot;
+use fractatoe::fractals::HistogramGeneration;
+
+const ITERATIONS: usize = 5000;
+
+fn builder() -> HistogramBuilder {
+    serde_json::from_str(
+        r#"{"width_px": 200, "height_px": 200, "resolution_px": 1,
+            "center": [-0.5, 0], "width_real": 3, "height_real": 3}"#,
+    )
+    .unwrap()
+}
+
+fn mandelbrot_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mandelbrot");
+    group.sample_size(10);
+
+    group.bench_function("naive", |b| {
+        b.iter(|| {
+            escape_time_histogram(&builder(), |(x, y)| {
+                let c = Complex::new(x, y);
+                iterate_until_escape(Complex::new(0., 0.), 0, |z| z * z + c, 4., ITERATIONS, None)
+            })
+        })
+    });
+
+    group.bench_function("interior_checks", |b| {
+        b.iter(|| {
+            let mandelbrot: Mandelbrot = serde_json::from_str(&format!(
+                r#"{{"bound": 4.0, "iterations": {}}}"#,
+                ITERATIONS
+            ))
+            .unwrap();
+            mandelbrot.build().unwrap().build_histogram(builder())
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, mandelbrot_benchmark);
+criterion_main!(benches);
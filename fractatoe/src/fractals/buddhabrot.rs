@@ -0,0 +1,128 @@
+use num::complex::Complex;
+use serde_derive::{Deserialize, Serialize};
+
+use rand::distributions::uniform::Uniform;
+use rand::Rng;
+use rand_seeder::Seeder;
+
+use super::escape_time::in_main_cardioid_or_bulb;
+use super::histogram::{Histogram, HistogramBuilder, Point};
+use super::HistogramGeneration;
+
+type BuddhabrotRng = rand::rngs::StdRng;
+
+/// Named histogram channel accumulating the orbits with their own iteration limit
+#[derive(Serialize, Deserialize)]
+pub struct IterationChannel {
+    pub name: String,
+    pub iterations: usize,
+}
+
+fn default_sample_area() -> (Point, Point) {
+    ((-2., -2.), (2., 2.))
+}
+
+/// Density of the orbits z -> z^2 + c of randomly sampled values of c. Only the escaping orbits
+/// are kept, or only the non-escaping ones for the Anti-Buddhabrot.
+///
+/// Every point of the kept orbits is accumulated in the frequency, and in each of the color
+/// channels whose own iteration limit is respected by the orbit (e.g. "red", "green" and "blue"
+/// with 5000, 500 and 50 iterations for a Nebulabrot).
+#[derive(Serialize, Deserialize)]
+pub struct Buddhabrot {
+    bound: f64,
+    iterations: usize,
+    #[serde(default)]
+    color_channels: Vec<IterationChannel>,
+    #[serde(default)]
+    anti: bool,
+
+    number_samples: usize,
+    /// Rectangle (lower corner, upper corner) in which the values of c are sampled
+    #[serde(default = "default_sample_area")]
+    sample_area: (Point, Point),
+    seed: String,
+}
+
+impl Buddhabrot {
+    /// Whether an orbit escaping at `escape` (None if it did not escape in the maximal number of
+    /// iterations) is kept with an iteration limit, and how many of its points are accumulated
+    fn orbit_length(&self, escape: Option<usize>, iterations: usize) -> Option<usize> {
+        match (escape, self.anti) {
+            (Some(escape), false) if escape < iterations => Some(escape),
+            (Some(escape), true) if escape >= iterations => Some(iterations),
+            (None, true) => Some(iterations),
+            _ => None,
+        }
+    }
+
+    fn run_worker(
+        &self,
+        worker: usize,
+        number_samples: usize,
+        builder: &HistogramBuilder,
+    ) -> Histogram {
+        let mut rng: BuddhabrotRng = Seeder::from((self.seed.as_str(), worker)).make_rng();
+        let ((x_min, y_min), (x_max, y_max)) = self.sample_area;
+        let uniform_distrib_x = Uniform::new(x_min, x_max);
+        let uniform_distrib_y = Uniform::new(y_min, y_max);
+
+        let mut histogram =
+            Histogram::new(builder.width_px, builder.height_px, builder.resolution_px);
+        let size = histogram.width * histogram.height * histogram.resolution * histogram.resolution;
+        for channel in self.color_channels.iter() {
+            histogram.add_channel(&channel.name, vec![0.; size]);
+        }
+
+        let max_iterations = self
+            .color_channels
+            .iter()
+            .map(|channel| channel.iterations)
+            .fold(self.iterations, usize::max);
+        let mut orbit: Vec<Option<(usize, usize)>> = Vec::with_capacity(max_iterations);
+
+        for _ in 0..number_samples {
+            let c = Complex::new(rng.sample(uniform_distrib_x), rng.sample(uniform_distrib_y));
+            // Those orbits never escape, no need to iterate them for the Buddhabrot
+            if !self.anti && in_main_cardioid_or_bulb(c) {
+                continue;
+            }
+
+            orbit.clear();
+            let mut z = Complex::new(0., 0.);
+            let mut escape = None;
+            for i in 0..max_iterations {
+                z = z * z + c;
+                if z.norm() > self.bound {
+                    escape = Some(i);
+                    break;
+                }
+                orbit.push(builder.real_to_pixel(z.re, z.im));
+            }
+
+            if let Some(length) = self.orbit_length(escape, self.iterations) {
+                for &(x, y) in orbit[..length].iter().flatten() {
+                    let (freq, color) = histogram.get_cell(x, y);
+                    histogram.set_cell(x, y, (freq + 1., color));
+                }
+            }
+            for (channel_index, channel) in self.color_channels.iter().enumerate() {
+                if let Some(length) = self.orbit_length(escape, channel.iterations) {
+                    for &(x, y) in orbit[..length].iter().flatten() {
+                        let value = histogram.get_channel_cell(channel_index, x, y);
+                        histogram.set_channel_cell(channel_index, x, y, value + 1.);
+                    }
+                }
+            }
+        }
+        histogram
+    }
+}
+
+impl HistogramGeneration for Buddhabrot {
+    fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
+        builder.par_accumulate(self.number_samples, |worker, number_samples| {
+            self.run_worker(worker, number_samples, &builder)
+        })
+    }
+}
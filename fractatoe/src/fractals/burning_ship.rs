@@ -0,0 +1,64 @@
+use num::complex::Complex;
+use serde_derive::{Deserialize, Serialize};
+
+use super::escape_time::{escape_time_histogram, iterate_until_escape};
+use super::histogram::{Histogram, HistogramBuilder};
+use super::HistogramGeneration;
+
+/// z -> (|Re(z)| + i|Im(z)|)^2 + c, iterated from 0 for every c of the plane
+#[derive(Serialize, Deserialize)]
+pub struct BurningShip {
+    bound: f64,
+    iterations: usize,
+    #[serde(default)]
+    smooth: bool,
+}
+
+/// z -> (|Re(z)| + i|Im(z)|)^2 + c for a fixed c, iterated from every z of the plane
+#[derive(Serialize, Deserialize)]
+pub struct BurningShipJulia {
+    c: (f64, f64),
+    bound: f64,
+    iterations: usize,
+    #[serde(default)]
+    smooth: bool,
+}
+
+fn burning_ship_step(z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+    let z_abs = Complex::new(z.re.abs(), z.im.abs());
+    z_abs * z_abs + c
+}
+
+impl HistogramGeneration for BurningShip {
+    fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
+        let smooth_degree = if self.smooth { Some(2.) } else { None };
+        escape_time_histogram(&builder, |(x_float, y_float)| {
+            let c = Complex::new(x_float, y_float);
+            iterate_until_escape(
+                Complex::new(0., 0.),
+                0,
+                |z| burning_ship_step(z, c),
+                self.bound,
+                self.iterations,
+                smooth_degree,
+            )
+        })
+    }
+}
+
+impl HistogramGeneration for BurningShipJulia {
+    fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
+        let smooth_degree = if self.smooth { Some(2.) } else { None };
+        let c = Complex::new(self.c.0, self.c.1);
+        escape_time_histogram(&builder, |(x_float, y_float)| {
+            iterate_until_escape(
+                Complex::new(x_float, y_float),
+                0,
+                |z| burning_ship_step(z, c),
+                self.bound,
+                self.iterations,
+                smooth_degree,
+            )
+        })
+    }
+}
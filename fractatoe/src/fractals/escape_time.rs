@@ -0,0 +1,142 @@
+use num::complex::Complex;
+
+use super::histogram::{Histogram, HistogramBuilder, Point};
+
+/// Channel set to 1 for the points which never escaped
+pub const INTERIOR_CHANNEL: &str = "interior";
+
+/// Channel holding the exterior distance estimate of the escaping points, in virtual pixels
+pub const DISTANCE_CHANNEL: &str = "distance";
+
+/// Result of the iteration of one point
+pub enum Escape {
+    /// The point escaped, with its (possibly fractional) iteration count
+    Escaped(f64),
+    /// The point did not escape within the iteration limit
+    Interior,
+}
+
+/// Fractional iteration count of a point which escaped at `iteration` with the value `z`, for an
+/// iteration of the form z -> z^degree + c (log-log renormalization). Continuous with respect to
+/// the starting point as long as the bound is large enough.
+pub fn smooth_iteration_count(iteration: usize, z: Complex<f64>, degree: f64) -> f64 {
+    iteration as f64 + 1. - z.norm().ln().ln() / degree.ln()
+}
+
+/// Exterior distance estimate of a point which escaped with the value `z`, `derivative` being the
+/// derivative of z with respect to this point. Within a factor 2 of the distance to the set (the
+/// Mandelbrot set) or to the Julia set, as long as the bound is large enough.
+pub fn exterior_distance_estimate(z: Complex<f64>, derivative: Complex<f64>) -> f64 {
+    let norm = z.norm();
+    norm * norm.ln() / derivative.norm()
+}
+
+/// Whether c lies in the main cardioid or in the period-2 bulb of the Mandelbrot set, in which
+/// case its orbit never escapes
+pub fn in_main_cardioid_or_bulb(c: Complex<f64>) -> bool {
+    let y_sq = c.im * c.im;
+    let q = (c.re - 0.25) * (c.re - 0.25) + y_sq;
+    q * (q + (c.re - 0.25)) <= 0.25 * y_sq || (c.re + 1.) * (c.re + 1.) + y_sq <= 0.0625
+}
+
+/// Iterate `step` from `z` (the value at iteration `first_iteration`) until its norm exceeds
+/// `bound`, or until `iterations` is reached. The smooth iteration count is returned if a degree is
+/// given.
+pub fn iterate_until_escape<F>(
+    mut z: Complex<f64>,
+    first_iteration: usize,
+    step: F,
+    bound: f64,
+    iterations: usize,
+    smooth_degree: Option<f64>,
+) -> Escape
+where
+    F: Fn(Complex<f64>) -> Complex<f64>,
+{
+    for i in first_iteration..iterations {
+        if z.norm() > bound {
+            return Escape::Escaped(match smooth_degree {
+                Some(degree) => smooth_iteration_count(i, z, degree),
+                None => i as f64,
+            });
+        }
+        z = step(z);
+    }
+    Escape::Interior
+}
+
+/// Build the histogram of an escape-time fractal in parallel: the frequency is the iteration
+/// count of the escaping points (0 for the others), and the interior channel flags the points
+/// which never escaped.
+pub fn escape_time_histogram<F>(builder: &HistogramBuilder, escape_function: F) -> Histogram
+where
+    F: Fn(Point) -> Escape + Sync,
+{
+    escape_time_histogram_with_channels(builder, &[], |point| (escape_function(point), vec![]))
+}
+
+/// Same as `escape_time_histogram`, the escape function also giving the values of the additional
+/// channels `channel_names` for each point
+pub fn escape_time_histogram_with_channels<F>(
+    builder: &HistogramBuilder,
+    channel_names: &[&str],
+    escape_function: F,
+) -> Histogram
+where
+    F: Fn(Point) -> (Escape, Vec<f64>) + Sync,
+{
+    escape_time_histogram_with_colors(builder, channel_names, |point| {
+        let (escape, values) = escape_function(point);
+        (escape, 0., values)
+    })
+}
+
+/// Same as `escape_time_histogram_with_channels`, the escape function also giving the color of
+/// each point
+pub fn escape_time_histogram_with_colors<F>(
+    builder: &HistogramBuilder,
+    channel_names: &[&str],
+    escape_function: F,
+) -> Histogram
+where
+    F: Fn(Point) -> (Escape, f64, Vec<f64>) + Sync,
+{
+    escape_time_histogram_from_escapes(
+        builder,
+        channel_names,
+        builder.par_map_pixels(escape_function),
+    )
+}
+
+/// Build the histogram of an escape-time fractal from the escapes, colors and values of the
+/// additional channels `channel_names` of every pixel, laid out like the histogram data
+pub fn escape_time_histogram_from_escapes(
+    builder: &HistogramBuilder,
+    channel_names: &[&str],
+    escapes: Vec<(Escape, f64, Vec<f64>)>,
+) -> Histogram {
+    let cells = escapes
+        .iter()
+        .map(|(escape, color, _)| match escape {
+            Escape::Escaped(iteration) => (*iteration, *color),
+            Escape::Interior => (0., *color),
+        })
+        .collect();
+    let interior = escapes
+        .iter()
+        .map(|(escape, _, _)| match escape {
+            Escape::Escaped(_) => 0.,
+            Escape::Interior => 1.,
+        })
+        .collect();
+
+    let mut histogram = Histogram::from_cells(builder, cells);
+    histogram.add_channel(INTERIOR_CHANNEL, interior);
+    for (index, name) in channel_names.iter().enumerate() {
+        histogram.add_channel(
+            name,
+            escapes.iter().map(|(_, _, values)| values[index]).collect(),
+        );
+    }
+    histogram
+}
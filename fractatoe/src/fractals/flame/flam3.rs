@@ -0,0 +1,640 @@
+//! Import and export of the flam3/Apophysis XML flame files.
+//!
+//! The camera (size, center, scale and oversampling) is converted to a histogram builder, and the
+//! quality to a number of points. The rotation of the camera and the rendering parameters
+//! (brightness, gamma, filter...) are ignored.
+
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use log::warn;
+
+use super::palette::{PaletteConf, PaletteSource};
+use super::variations::VariationFunction;
+use super::{CoefFlame, FlameConf, Symmetry, WeightedVariation, Xform};
+use crate::fractals::histogram::HistogramBuilder;
+
+/// Iterations of each point of an imported flame, the total number of iterations being given by
+/// the quality
+const NUMBER_ITERATIONS: usize = 1000;
+const ITERATION_OFFSET: usize = 20;
+
+#[derive(Debug)]
+pub struct Flam3Error {
+    pub message: String,
+}
+
+impl fmt::Display for Flam3Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid flame file: {}", self.message)
+    }
+}
+
+impl std::error::Error for Flam3Error {}
+
+impl From<roxmltree::Error> for Flam3Error {
+    fn from(error: roxmltree::Error) -> Self {
+        Flam3Error {
+            message: error.to_string(),
+        }
+    }
+}
+
+fn error<T>(message: String) -> Result<T, Flam3Error> {
+    Err(Flam3Error { message })
+}
+
+/// Attributes of the xforms which are not variations
+const XFORM_ATTRIBUTES: &[&str] = &[
+    "weight",
+    "color",
+    "symmetry",
+    "color_speed",
+    "coefs",
+    "post",
+    "chaos",
+    "opacity",
+    "name",
+    "animate",
+    "var_color",
+    "plotmode",
+];
+
+/// Variations with their flam3 name, and the names and default values of their parameters
+const VARIATIONS: &[(&str, &[(&str, f64)])] = &[
+    ("linear", &[]),
+    ("sinusoidal", &[]),
+    ("spherical", &[]),
+    ("swirl", &[]),
+    ("horseshoe", &[]),
+    ("polar", &[]),
+    ("handkerchief", &[]),
+    ("heart", &[]),
+    ("disc", &[]),
+    ("spiral", &[]),
+    ("hyperbolic", &[]),
+    ("diamond", &[]),
+    ("ex", &[]),
+    ("julia", &[]),
+    ("bent", &[]),
+    ("waves", &[]),
+    ("fisheye", &[]),
+    ("popcorn", &[]),
+    ("exponential", &[]),
+    ("power", &[]),
+    ("cosine", &[]),
+    ("rings", &[]),
+    ("fan", &[]),
+    (
+        "blob",
+        &[("blob_high", 1.), ("blob_low", 0.), ("blob_waves", 1.)],
+    ),
+    (
+        "pdj",
+        &[("pdj_a", 0.), ("pdj_b", 0.), ("pdj_c", 0.), ("pdj_d", 0.)],
+    ),
+    ("fan2", &[("fan2_x", 0.), ("fan2_y", 0.)]),
+    ("rings2", &[("rings2_val", 0.)]),
+    ("eyefish", &[]),
+    ("bubble", &[]),
+    ("cylinder", &[]),
+    (
+        "perspective",
+        &[("perspective_angle", 0.), ("perspective_dist", 0.)],
+    ),
+    ("noise", &[]),
+    ("julian", &[("julian_power", 1.), ("julian_dist", 1.)]),
+    (
+        "juliascope",
+        &[("juliascope_power", 1.), ("juliascope_dist", 1.)],
+    ),
+    ("blur", &[]),
+    ("gaussian_blur", &[]),
+    ("radial_blur", &[("radial_blur_angle", 0.)]),
+    (
+        "pie",
+        &[
+            ("pie_slices", 6.),
+            ("pie_rotation", 0.),
+            ("pie_thickness", 0.5),
+        ],
+    ),
+    (
+        "ngon",
+        &[
+            ("ngon_power", 3.),
+            ("ngon_sides", 5.),
+            ("ngon_corners", 2.),
+            ("ngon_circle", 1.),
+        ],
+    ),
+    ("curl", &[("curl_c1", 1.), ("curl_c2", 0.)]),
+    ("rectangles", &[("rectangles_x", 1.), ("rectangles_y", 1.)]),
+    ("arch", &[]),
+    ("tangent", &[]),
+    ("square", &[]),
+    ("rays", &[]),
+    ("blade", &[]),
+    ("secant2", &[]),
+    ("twintrian", &[]),
+    ("cross", &[]),
+];
+
+/// Variation of the given flam3 name, with its parameters in the order of `VARIATIONS`
+fn variation_from_flam3(name: &str, p: &[f64]) -> VariationFunction {
+    match name {
+        "linear" => VariationFunction::Linear,
+        "sinusoidal" => VariationFunction::Sinusoidal,
+        "spherical" => VariationFunction::Spherical,
+        "swirl" => VariationFunction::Swirl,
+        "horseshoe" => VariationFunction::Horseshoe,
+        "polar" => VariationFunction::Polar,
+        "handkerchief" => VariationFunction::Handkerchief,
+        "heart" => VariationFunction::Heart,
+        "disc" => VariationFunction::Disc,
+        "spiral" => VariationFunction::Spiral,
+        "hyperbolic" => VariationFunction::Hyperbolic,
+        "diamond" => VariationFunction::Diamond,
+        "ex" => VariationFunction::Ex,
+        "julia" => VariationFunction::Flam3Julia,
+        "bent" => VariationFunction::Bent,
+        "waves" => VariationFunction::Waves,
+        "fisheye" => VariationFunction::Fisheye,
+        "popcorn" => VariationFunction::Popcorn,
+        "exponential" => VariationFunction::Exponential,
+        "power" => VariationFunction::Power,
+        "cosine" => VariationFunction::Cosine,
+        "rings" => VariationFunction::Rings,
+        "fan" => VariationFunction::Fan,
+        "blob" => VariationFunction::Blob {
+            high: p[0],
+            low: p[1],
+            waves: p[2],
+        },
+        "pdj" => VariationFunction::Pdj {
+            a: p[0],
+            b: p[1],
+            c: p[2],
+            d: p[3],
+        },
+        "fan2" => VariationFunction::Fan2 { x: p[0], y: p[1] },
+        "rings2" => VariationFunction::Rings2 { val: p[0] },
+        "eyefish" => VariationFunction::Eyefish,
+        "bubble" => VariationFunction::Bubble,
+        "cylinder" => VariationFunction::Cylinder,
+        "perspective" => VariationFunction::Perspective {
+            angle: p[0],
+            dist: p[1],
+        },
+        "noise" => VariationFunction::Noise,
+        "julian" => VariationFunction::Julian {
+            power: p[0],
+            dist: p[1],
+        },
+        "juliascope" => VariationFunction::Juliascope {
+            power: p[0],
+            dist: p[1],
+        },
+        "blur" => VariationFunction::Blur,
+        "gaussian_blur" => VariationFunction::GaussianBlur,
+        "radial_blur" => VariationFunction::RadialBlur { angle: p[0] },
+        "pie" => VariationFunction::Pie {
+            slices: p[0],
+            rotation: p[1],
+            thickness: p[2],
+        },
+        "ngon" => VariationFunction::Ngon {
+            power: p[0],
+            sides: p[1],
+            corners: p[2],
+            circle: p[3],
+        },
+        "curl" => VariationFunction::Curl { c1: p[0], c2: p[1] },
+        "rectangles" => VariationFunction::Rectangles { x: p[0], y: p[1] },
+        "arch" => VariationFunction::Arch,
+        "tangent" => VariationFunction::Tangent,
+        "square" => VariationFunction::Square,
+        "rays" => VariationFunction::Rays,
+        "blade" => VariationFunction::Blade,
+        "secant2" => VariationFunction::Secant2,
+        "twintrian" => VariationFunction::Twintrian,
+        "cross" => VariationFunction::Cross,
+        _ => unreachable!("Variation {} missing from the flam3 table", name),
+    }
+}
+
+/// flam3 name of the variation, with its parameters in the order of `VARIATIONS`. The
+/// variations which flam3 does not have are refused.
+fn variation_to_flam3(
+    variation: VariationFunction,
+) -> Result<(&'static str, Vec<f64>), Flam3Error> {
+    Ok(match variation {
+        VariationFunction::Bisin => {
+            warn!("The bisin variation is exported as sinusoidal, which is the same function");
+            ("sinusoidal", vec![])
+        }
+        VariationFunction::Linear => ("linear", vec![]),
+        VariationFunction::Sinusoidal => ("sinusoidal", vec![]),
+        VariationFunction::Spherical => ("spherical", vec![]),
+        VariationFunction::Swirl => ("swirl", vec![]),
+        VariationFunction::Horseshoe => ("horseshoe", vec![]),
+        VariationFunction::Polar => ("polar", vec![]),
+        VariationFunction::Handkerchief => ("handkerchief", vec![]),
+        VariationFunction::Heart => ("heart", vec![]),
+        VariationFunction::Disc => ("disc", vec![]),
+        VariationFunction::Spiral => ("spiral", vec![]),
+        VariationFunction::Hyperbolic => ("hyperbolic", vec![]),
+        VariationFunction::Diamond => ("diamond", vec![]),
+        VariationFunction::Ex => ("ex", vec![]),
+        VariationFunction::Julia => {
+            return error(
+                "the original julia variation is not in flam3, whose julia variation is \
+                 Flam3Julia"
+                    .to_string(),
+            )
+        }
+        VariationFunction::Flam3Julia => ("julia", vec![]),
+        VariationFunction::Bent => ("bent", vec![]),
+        VariationFunction::Waves => ("waves", vec![]),
+        VariationFunction::Fisheye => ("fisheye", vec![]),
+        VariationFunction::Popcorn => ("popcorn", vec![]),
+        VariationFunction::Exponential => ("exponential", vec![]),
+        VariationFunction::Power => ("power", vec![]),
+        VariationFunction::Cosine => ("cosine", vec![]),
+        VariationFunction::Rings => ("rings", vec![]),
+        VariationFunction::Fan => ("fan", vec![]),
+        VariationFunction::Blob { high, low, waves } => ("blob", vec![high, low, waves]),
+        VariationFunction::Pdj { a, b, c, d } => ("pdj", vec![a, b, c, d]),
+        VariationFunction::Fan2 { x, y } => ("fan2", vec![x, y]),
+        VariationFunction::Rings2 { val } => ("rings2", vec![val]),
+        VariationFunction::Eyefish => ("eyefish", vec![]),
+        VariationFunction::Bubble => ("bubble", vec![]),
+        VariationFunction::Cylinder => ("cylinder", vec![]),
+        VariationFunction::Perspective { angle, dist } => ("perspective", vec![angle, dist]),
+        VariationFunction::Noise => ("noise", vec![]),
+        VariationFunction::Julian { power, dist } => ("julian", vec![power, dist]),
+        VariationFunction::Juliascope { power, dist } => ("juliascope", vec![power, dist]),
+        VariationFunction::Blur => ("blur", vec![]),
+        VariationFunction::GaussianBlur => ("gaussian_blur", vec![]),
+        VariationFunction::RadialBlur { angle } => ("radial_blur", vec![angle]),
+        VariationFunction::Pie {
+            slices,
+            rotation,
+            thickness,
+        } => ("pie", vec![slices, rotation, thickness]),
+        VariationFunction::Ngon {
+            power,
+            sides,
+            corners,
+            circle,
+        } => ("ngon", vec![power, sides, corners, circle]),
+        VariationFunction::Curl { c1, c2 } => ("curl", vec![c1, c2]),
+        VariationFunction::Rectangles { x, y } => ("rectangles", vec![x, y]),
+        VariationFunction::Arch => ("arch", vec![]),
+        VariationFunction::Tangent => ("tangent", vec![]),
+        VariationFunction::Square => ("square", vec![]),
+        VariationFunction::Rays => ("rays", vec![]),
+        VariationFunction::Blade => ("blade", vec![]),
+        VariationFunction::Secant2 => ("secant2", vec![]),
+        VariationFunction::Twintrian => ("twintrian", vec![]),
+        VariationFunction::Cross => ("cross", vec![]),
+    })
+}
+
+fn parse_attribute<T: FromStr>(node: roxmltree::Node, name: &str) -> Result<Option<T>, Flam3Error> {
+    match node.attribute(name) {
+        None => Ok(None),
+        Some(value) => match value.trim().parse() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => error(format!("invalid value {} of the attribute {}", value, name)),
+        },
+    }
+}
+
+/// Attribute made of `count` numbers separated by spaces
+fn parse_numbers(
+    node: roxmltree::Node,
+    name: &str,
+    count: Option<usize>,
+) -> Result<Option<Vec<f64>>, Flam3Error> {
+    let value = match node.attribute(name) {
+        None => return Ok(None),
+        Some(value) => value,
+    };
+    let numbers = value
+        .split_whitespace()
+        .map(|number| number.parse())
+        .collect::<Result<Vec<f64>, _>>();
+    match numbers {
+        Ok(numbers) if count.is_none() || count == Some(numbers.len()) => Ok(Some(numbers)),
+        _ => error(format!("invalid value {} of the attribute {}", value, name)),
+    }
+}
+
+/// flam3 coefficients are given column by column: a d b e c f
+fn coefs_from_flam3(coefs: &[f64]) -> CoefFlame {
+    (coefs[0], coefs[2], coefs[4], coefs[1], coefs[3], coefs[5])
+}
+
+fn coefs_to_flam3(coefs: CoefFlame) -> String {
+    format!(
+        "{} {} {} {} {} {}",
+        coefs.0, coefs.3, coefs.1, coefs.4, coefs.2, coefs.5
+    )
+}
+
+fn parse_xform(node: roxmltree::Node) -> Result<Xform, Flam3Error> {
+    let coefs = match parse_numbers(node, "coefs", Some(6))? {
+        Some(coefs) => coefs_from_flam3(&coefs),
+        None => return error("xform without coefs".to_string()),
+    };
+    let post = parse_numbers(node, "post", Some(6))?.map(|post| coefs_from_flam3(&post));
+
+    // Older files give the symmetry s of the transform, the color speed being (1 - s) / 2
+    let color_speed = match parse_attribute::<f64>(node, "color_speed")? {
+        Some(color_speed) => color_speed,
+        None => (1. - parse_attribute::<f64>(node, "symmetry")?.unwrap_or(0.)) / 2.,
+    };
+
+    let mut variations = vec![];
+    for attribute in node.attributes() {
+        let name = attribute.name();
+        if XFORM_ATTRIBUTES.contains(&name) {
+            continue;
+        }
+        match VARIATIONS.iter().find(|(variation, _)| *variation == name) {
+            Some((_, parameters)) => {
+                let mut values = vec![];
+                for (parameter, default) in parameters.iter() {
+                    values.push(parse_attribute(node, parameter)?.unwrap_or(*default));
+                }
+                variations.push(WeightedVariation {
+                    variation: variation_from_flam3(name, &values),
+                    weight: parse_attribute(node, name)?.unwrap_or(0.),
+                });
+            }
+            None => {
+                let is_parameter = VARIATIONS.iter().any(|(_, parameters)| {
+                    parameters.iter().any(|(parameter, _)| *parameter == name)
+                });
+                if !is_parameter {
+                    return error(format!("unsupported variation {}", name));
+                }
+            }
+        }
+    }
+
+    Ok(Xform {
+        weight: parse_attribute(node, "weight")?.unwrap_or(1.),
+        coefs,
+        variations,
+        post,
+        color: parse_attribute(node, "color")?.unwrap_or(0.),
+        color_speed,
+        xaos: parse_numbers(node, "chaos", None)?,
+    })
+}
+
+/// 256 colors of the palette, given either by color elements or by an hexadecimal palette element,
+/// and rotated by the hue of the flame
+fn parse_palette(flame: roxmltree::Node) -> Result<Option<PaletteConf>, Flam3Error> {
+    let mut palette = vec![];
+    for color in flame.children().filter(|node| node.has_tag_name("color")) {
+        let index: usize = parse_attribute(color, "index")?.unwrap_or(palette.len());
+        let rgb = match parse_numbers(color, "rgb", Some(3))? {
+            Some(rgb) => (rgb[0] as u8, rgb[1] as u8, rgb[2] as u8),
+            None => return error("color without rgb".to_string()),
+        };
+        if index >= palette.len() {
+            palette.resize(index + 1, (0, 0, 0));
+        }
+        palette[index] = rgb;
+    }
+
+    if let Some(hex_palette) = flame.children().find(|node| node.has_tag_name("palette")) {
+        let digits: Vec<char> = hex_palette
+            .text()
+            .unwrap_or("")
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        let bytes = digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16))
+            .collect::<Result<Vec<u8>, _>>();
+        match bytes {
+            Ok(bytes) if bytes.len() % 3 == 0 => {
+                palette = bytes
+                    .chunks(3)
+                    .map(|rgb| (rgb[0], rgb[1], rgb[2]))
+                    .collect()
+            }
+            _ => return error("invalid hexadecimal palette".to_string()),
+        }
+    }
+
+    Ok(if palette.is_empty() {
+        None
+    } else {
+        Some(PaletteConf {
+            source: PaletteSource::Colors(palette),
+            hue_rotation: parse_attribute(flame, "hue")?.unwrap_or(0.),
+        })
+    })
+}
+
+/// Read the first flame of a flam3 file, with the viewport of its camera
+pub fn from_flam3(xml: &str) -> Result<(HistogramBuilder, FlameConf), Flam3Error> {
+    let document = roxmltree::Document::parse(xml)?;
+    let flame = match document
+        .descendants()
+        .find(|node| node.has_tag_name("flame"))
+    {
+        Some(flame) => flame,
+        None => return error("no flame element".to_string()),
+    };
+
+    let size = match parse_numbers(flame, "size", Some(2))? {
+        Some(size) => size,
+        None => return error("flame without size".to_string()),
+    };
+    let (width_px, height_px) = (size[0] as usize, size[1] as usize);
+    let center = parse_numbers(flame, "center", Some(2))?.unwrap_or_else(|| vec![0., 0.]);
+    let zoom: f64 = parse_attribute(flame, "zoom")?.unwrap_or(0.);
+    // Number of pixels per unit
+    let scale = parse_attribute::<f64>(flame, "scale")?.unwrap_or(1.) * zoom.exp2();
+    let oversample = parse_attribute(flame, "oversample")?.unwrap_or(1);
+    if parse_attribute::<f64>(flame, "rotate")?.unwrap_or(0.) != 0. {
+        warn!("The rotation of the flame camera is ignored");
+    }
+    let builder = HistogramBuilder::new(
+        width_px,
+        height_px,
+        oversample,
+        (center[0], center[1]),
+        width_px as f64 / scale,
+        height_px as f64 / scale,
+    );
+
+    let xforms = flame
+        .children()
+        .filter(|node| node.has_tag_name("xform"))
+        .map(parse_xform)
+        .collect::<Result<Vec<Xform>, Flam3Error>>()?;
+    if xforms.is_empty() {
+        return error("flame without xform".to_string());
+    }
+    let final_xform = match flame
+        .children()
+        .find(|node| node.has_tag_name("finalxform"))
+    {
+        Some(node) => Some(parse_xform(node)?),
+        None => None,
+    };
+
+    // As in flam3, a negative symmetry is dihedral
+    let symmetry = match parse_attribute::<i64>(flame, "symmetry")?.unwrap_or(0) {
+        order if order < 0 => Some(Symmetry::Dihedral(-order as usize)),
+        order if order > 1 => Some(Symmetry::Rotational(order as usize)),
+        _ => None,
+    };
+
+    // The quality is the number of iterations per pixel of the image
+    let quality = parse_attribute::<f64>(flame, "quality")?.unwrap_or(50.);
+    let number_points =
+        (quality * (width_px * height_px) as f64 / NUMBER_ITERATIONS as f64).round() as usize;
+
+    let flame_conf = FlameConf {
+        xforms,
+        final_xform,
+        palette: parse_palette(flame)?,
+        symmetry,
+        number_points: number_points.max(1),
+        iteration_offset: ITERATION_OFFSET,
+        number_iterations: NUMBER_ITERATIONS,
+        seed: flame.attribute("name").unwrap_or("flam3").to_string(),
+    };
+    Ok((builder, flame_conf))
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn write_xform(xml: &mut String, tag: &str, xform: &Xform) -> Result<(), Flam3Error> {
+    write!(
+        xml,
+        "  <{} weight=\"{}\" color=\"{}\" color_speed=\"{}\" coefs=\"{}\"",
+        tag,
+        xform.weight,
+        xform.color,
+        xform.color_speed,
+        coefs_to_flam3(xform.coefs)
+    )
+    .unwrap();
+    if let Some(post) = xform.post {
+        write!(xml, " post=\"{}\"", coefs_to_flam3(post)).unwrap();
+    }
+    if let Some(xaos) = &xform.xaos {
+        let xaos: Vec<String> = xaos.iter().map(f64::to_string).collect();
+        write!(xml, " chaos=\"{}\"", xaos.join(" ")).unwrap();
+    }
+
+    let names: Vec<(&str, Vec<f64>)> = xform
+        .variations
+        .iter()
+        .map(|weighted| variation_to_flam3(weighted.variation))
+        .collect::<Result<_, _>>()?;
+    for (index, (name, values)) in names.iter().enumerate() {
+        // The same variation twice in a transform is merged, as flam3 allows it only once
+        if names[..index].iter().any(|(previous, _)| previous == name) {
+            continue;
+        }
+        let weight: f64 = xform
+            .variations
+            .iter()
+            .zip(names.iter())
+            .filter(|(_, (other, _))| other == name)
+            .map(|(weighted, _)| weighted.weight)
+            .sum();
+        write!(xml, " {}=\"{}\"", name, weight).unwrap();
+
+        let (_, parameters) = VARIATIONS
+            .iter()
+            .find(|(variation, _)| variation == name)
+            .expect("Variation missing from the flam3 table");
+        for ((parameter, _), value) in parameters.iter().zip(values.iter()) {
+            write!(xml, " {}=\"{}\"", parameter, value).unwrap();
+        }
+    }
+    xml.push_str("/>\n");
+    Ok(())
+}
+
+/// flam3 file of the flame seen through the viewport. The histogram must have square pixels.
+pub fn to_flam3(builder: &HistogramBuilder, flame: &FlameConf) -> Result<String, Flam3Error> {
+    let scale = builder.width_px as f64 / builder.width_real();
+    let quality = (flame.number_points * flame.number_iterations) as f64
+        / (builder.width_px * builder.height_px) as f64;
+
+    let mut attributes = match flame.symmetry {
+        Some(Symmetry::Rotational(order)) => format!(" symmetry=\"{}\"", order),
+        Some(Symmetry::Dihedral(order)) => format!(" symmetry=\"-{}\"", order),
+        None => String::new(),
+    };
+    match &flame.palette {
+        Some(palette) if palette.hue_rotation != 0. => {
+            write!(attributes, " hue=\"{}\"", palette.hue_rotation).unwrap()
+        }
+        _ => (),
+    }
+
+    let mut xml = String::new();
+    writeln!(
+        xml,
+        "<flame name=\"{}\" size=\"{} {}\" center=\"{} {}\" scale=\"{}\" oversample=\"{}\" \
+         quality=\"{}\"{}>",
+        escape_xml(&flame.seed),
+        builder.width_px,
+        builder.height_px,
+        builder.center().0,
+        builder.center().1,
+        scale,
+        builder.resolution_px,
+        quality,
+        attributes
+    )
+    .unwrap();
+
+    for xform in flame.xforms.iter() {
+        write_xform(&mut xml, "xform", xform)?;
+    }
+    if let Some(final_xform) = &flame.final_xform {
+        write_xform(&mut xml, "finalxform", final_xform)?;
+    }
+    if let Some(palette) = &flame.palette {
+        match palette.source.interpolated_colors() {
+            Ok(colors) => {
+                for (index, rgb) in colors.iter().enumerate() {
+                    let channel = |value: f64| (value * 255.).round() as u8;
+                    writeln!(
+                        xml,
+                        "  <color index=\"{}\" rgb=\"{} {} {}\"/>",
+                        index,
+                        channel(rgb[0]),
+                        channel(rgb[1]),
+                        channel(rgb[2])
+                    )
+                    .unwrap();
+                }
+            }
+            Err(error) => warn!("The palette of the flame is not exported: {}", error),
+        }
+    }
+    xml.push_str("</flame>\n");
+    Ok(xml)
+}
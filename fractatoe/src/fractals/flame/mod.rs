@@ -0,0 +1,513 @@
+use serde_derive::{Deserialize, Serialize};
+
+use rand_seeder::Seeder;
+
+use std::convert::TryFrom;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use log::{info, warn};
+use rand::distributions::uniform::Uniform;
+use rand::distributions::weighted::{WeightedError, WeightedIndex};
+use rand::Rng;
+
+use super::HistogramGeneration;
+use crate::fractals::histogram::{F64Color, Histogram, HistogramBuilder};
+
+pub mod flam3;
+pub mod palette;
+pub mod variations;
+
+use palette::{Palette, PaletteConf};
+use variations::{FlameFunction, VariationFunction};
+
+type FlameRng = rand::rngs::StdRng;
+
+pub type FlameDistribution = WeightedIndex<f64>;
+// a_j, b_j, c_j, d_j, e_j, f_j j=1...n
+type CoefFlame = (f64, f64, f64, f64, f64, f64);
+
+type FlamePoint = ((f64, f64), F64Color);
+
+/// Points beyond this distance from the origin are considered diverging, as in flam3
+const RUNAWAY_BOUND: f64 = 1e10;
+
+/// Non-finite points, produced e.g. by the singularities of the variations, or diverging ones
+fn is_runaway(((x, y), _): FlamePoint) -> bool {
+    !(x.abs() < RUNAWAY_BOUND && y.abs() < RUNAWAY_BOUND)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WeightedVariation {
+    variation: VariationFunction,
+    weight: f64,
+}
+
+fn default_weight() -> f64 {
+    1.
+}
+
+fn default_color_speed() -> f64 {
+    0.5
+}
+
+/// One transform of the flame: an affine map followed by a weighted sum of variations, and
+/// optionally by a second affine map
+#[derive(Serialize, Deserialize)]
+pub struct Xform {
+    /// Relative probability to pick this transform, ignored for the final transform
+    #[serde(default = "default_weight")]
+    weight: f64,
+    coefs: CoefFlame,
+    variations: Vec<WeightedVariation>,
+    /// Coefficients of the affine map applied after the variations
+    #[serde(default)]
+    post: Option<CoefFlame>,
+    /// Color index of the transform, between 0 and 1
+    color: f64,
+    /// Proportion of the transform color blended into the point color at each application
+    #[serde(default = "default_color_speed")]
+    color_speed: f64,
+    /// Multipliers of the weights of the transforms picked right after this one (xaos), the
+    /// missing ones being 1
+    #[serde(default)]
+    xaos: Option<Vec<f64>>,
+}
+
+impl Xform {
+    fn build(self) -> XformAlgorithm {
+        let coefs = self.coefs;
+        XformAlgorithm {
+            coefs,
+            variations: self
+                .variations
+                .into_iter()
+                // Some variations divide by their weight
+                .filter(|weighted| weighted.weight != 0.)
+                .map(|weighted| {
+                    (
+                        weighted.variation.build(weighted.weight, coefs),
+                        weighted.weight,
+                    )
+                })
+                .collect(),
+            post: self.post,
+            color: self.color,
+            color_speed: self.color_speed,
+        }
+    }
+}
+
+/// Symmetry group given to a flame by adding the transforms generating it
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Symmetry {
+    /// Rotations by multiples of 1/n turn around the origin
+    Rotational(usize),
+    /// Rotations by multiples of 1/n turn and the reflection across the vertical axis
+    Dihedral(usize),
+}
+
+impl Symmetry {
+    /// Transforms generating the group, each one with the given weight. They don't change the
+    /// color of the points, so that the symmetric parts look the same.
+    fn xforms(self, weight: f64) -> Vec<Xform> {
+        let (order, reflection) = match self {
+            Symmetry::Rotational(order) => (order, false),
+            Symmetry::Dihedral(order) => (order, true),
+        };
+        let symmetry_xform = |coefs| Xform {
+            weight,
+            coefs,
+            variations: vec![WeightedVariation {
+                variation: VariationFunction::Linear,
+                weight: 1.,
+            }],
+            post: None,
+            color: 0.,
+            color_speed: 0.,
+            xaos: None,
+        };
+
+        let mut xforms = vec![];
+        if reflection {
+            xforms.push(symmetry_xform((-1., 0., 0., 0., 1., 0.)));
+        }
+        for k in 1..order {
+            let (sin, cos) = (2. * std::f64::consts::PI * k as f64 / order as f64).sin_cos();
+            xforms.push(symmetry_xform((cos, -sin, 0., sin, cos, 0.)));
+        }
+        xforms
+    }
+}
+
+/// Original format of the flames: every transform applies the same variation weights, and the
+/// points keep their initial random color
+#[derive(Deserialize)]
+struct FlatFlameConf {
+    variation_functions: Vec<VariationFunction>,
+    flame_distribution: Vec<u8>,
+    weight_variation: Vec<f64>,
+    coefs_inside: Vec<CoefFlame>,
+
+    number_points: usize,
+    iteration_offset: usize,
+    number_iterations: usize,
+
+    seed: String,
+}
+
+#[derive(Deserialize)]
+struct XformFlameConf {
+    xforms: Vec<Xform>,
+    #[serde(default)]
+    final_xform: Option<Xform>,
+    #[serde(default)]
+    palette: Option<PaletteConf>,
+    #[serde(default)]
+    symmetry: Option<Symmetry>,
+
+    number_points: usize,
+    iteration_offset: usize,
+    number_iterations: usize,
+
+    seed: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FlameConfFormat {
+    Xforms(XformFlameConf),
+    Flat(FlatFlameConf),
+}
+
+impl TryFrom<FlameConfFormat> for FlameConf {
+    type Error = String;
+
+    fn try_from(format: FlameConfFormat) -> Result<Self, Self::Error> {
+        Ok(match format {
+            FlameConfFormat::Xforms(conf) => FlameConf {
+                xforms: conf.xforms,
+                final_xform: conf.final_xform,
+                palette: conf.palette,
+                symmetry: conf.symmetry,
+                number_points: conf.number_points,
+                iteration_offset: conf.iteration_offset,
+                number_iterations: conf.number_iterations,
+                seed: conf.seed,
+            },
+            FlameConfFormat::Flat(conf) => {
+                if conf.coefs_inside.len() != conf.flame_distribution.len() {
+                    return Err(format!(
+                        "{} affine coefficients for {} transform weights",
+                        conf.coefs_inside.len(),
+                        conf.flame_distribution.len()
+                    ));
+                }
+                if conf.variation_functions.len() != conf.weight_variation.len() {
+                    return Err(format!(
+                        "{} variations for {} variation weights",
+                        conf.variation_functions.len(),
+                        conf.weight_variation.len()
+                    ));
+                }
+                let variation_functions = conf.variation_functions;
+                let weight_variation = conf.weight_variation;
+                let xforms = conf
+                    .coefs_inside
+                    .into_iter()
+                    .zip(conf.flame_distribution)
+                    .map(|(coefs, weight)| Xform {
+                        weight: weight as f64,
+                        coefs,
+                        variations: variation_functions
+                            .iter()
+                            .zip(weight_variation.iter())
+                            .map(|(&variation, &weight)| WeightedVariation { variation, weight })
+                            .collect(),
+                        post: None,
+                        color: 0.,
+                        color_speed: 0.,
+                        xaos: None,
+                    })
+                    .collect();
+                FlameConf {
+                    xforms,
+                    final_xform: None,
+                    palette: None,
+                    symmetry: None,
+                    number_points: conf.number_points,
+                    iteration_offset: conf.iteration_offset,
+                    number_iterations: conf.number_iterations,
+                    seed: conf.seed,
+                }
+            }
+        })
+    }
+}
+
+/// Flame genome. The original flat format, with one list of variation weights shared by every
+/// transform, is still accepted.
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "FlameConfFormat")]
+pub struct FlameConf {
+    xforms: Vec<Xform>,
+    /// Transform applied to the points before they are plotted, without being fed back into the
+    /// iteration
+    final_xform: Option<Xform>,
+    /// Colors indexed by the color of the points, from 0 to 1, accumulated in the red, green and
+    /// blue channels of the histogram
+    palette: Option<PaletteConf>,
+    /// Symmetry of the flame, whose generating transforms are added to `xforms`
+    symmetry: Option<Symmetry>,
+
+    number_points: usize,
+    iteration_offset: usize,
+    number_iterations: usize,
+
+    seed: String,
+}
+
+impl FlameConf {
+    /// Build the flame, loading its palette
+    pub fn build(self) -> io::Result<FlameAlgorithm> {
+        let mut xforms = self.xforms;
+        if let Some(symmetry) = self.symmetry {
+            // The more often the symmetries are applied, the more evenly the points are spread
+            // over the symmetric parts. Like in flam3 with normalized weights, each one is as
+            // likely as all the other transforms together.
+            let total_weight = xforms.iter().map(|xform| xform.weight).sum();
+            xforms.extend(symmetry.xforms(total_weight));
+        }
+
+        let weights: Vec<f64> = xforms.iter().map(|xform| xform.weight).collect();
+        let flame_distribution = FlameDistribution::new(&weights).map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Flame function distribution not computable: {}", error),
+            )
+        })?;
+
+        // One distribution per transform, only if some transform constrains its successors
+        let xaos_distributions = if xforms.iter().any(|xform| xform.xaos.is_some()) {
+            Some(
+                xforms
+                    .iter()
+                    .enumerate()
+                    .map(|(i, xform)| {
+                        let xaos = xform.xaos.as_deref().unwrap_or(&[]);
+                        let xaos_weights = weights
+                            .iter()
+                            .enumerate()
+                            .map(|(j, weight)| weight * xaos.get(j).copied().unwrap_or(1.));
+                        match FlameDistribution::new(xaos_weights) {
+                            // Like in flam3, a transform allowing no successor is followed by
+                            // any of them
+                            Err(WeightedError::AllWeightsZero) => {
+                                warn!("Transform {} has a null xaos, ignoring it", i);
+                                Ok(flame_distribution.clone())
+                            }
+                            distribution => distribution.map_err(|error| {
+                                io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    format!(
+                                        "Xaos distribution of transform {} not computable: {}",
+                                        i, error
+                                    ),
+                                )
+                            }),
+                        }
+                    })
+                    .collect::<io::Result<_>>()?,
+            )
+        } else {
+            None
+        };
+
+        let xforms = xforms.into_iter().map(Xform::build).collect();
+
+        Ok(FlameAlgorithm {
+            xforms,
+            final_xform: self.final_xform.map(Xform::build),
+            palette: self.palette.map(|palette| palette.build()).transpose()?,
+            flame_distribution,
+            xaos_distributions,
+            number_points: self.number_points,
+            number_iterations: self.number_iterations,
+            iteration_offset: self.iteration_offset,
+
+            seed: self.seed,
+        })
+    }
+}
+
+struct XformAlgorithm {
+    coefs: CoefFlame,
+    variations: Vec<(FlameFunction, f64)>,
+    post: Option<CoefFlame>,
+    color: f64,
+    color_speed: f64,
+}
+
+fn apply_affine(coefs: CoefFlame, x: f64, y: f64) -> (f64, f64) {
+    (
+        coefs.0 * x + coefs.1 * y + coefs.2,
+        coefs.3 * x + coefs.4 * y + coefs.5,
+    )
+}
+
+impl XformAlgorithm {
+    fn apply(&self, point: FlamePoint, rng: &mut FlameRng) -> FlamePoint {
+        let (mut x_current, mut y_current) = (0., 0.);
+        let (x_point, y_point) = point.0;
+        let (x_affine, y_affine) = apply_affine(self.coefs, x_point, y_point);
+
+        for (variation_function, weight) in self.variations.iter() {
+            let (x_translate, y_translate) = variation_function(x_affine, y_affine, rng);
+
+            x_current += weight * x_translate;
+            y_current += weight * y_translate;
+        }
+
+        if let Some(post) = self.post {
+            let (x_post, y_post) = apply_affine(post, x_current, y_current);
+            x_current = x_post;
+            y_current = y_post;
+        }
+
+        // The point color drifts toward the color of the transform
+        let color = point.1 * (1. - self.color_speed) + self.color * self.color_speed;
+
+        ((x_current, y_current), color)
+    }
+}
+
+pub struct FlameAlgorithm {
+    xforms: Vec<XformAlgorithm>,
+    final_xform: Option<XformAlgorithm>,
+    flame_distribution: FlameDistribution,
+    /// Distributions of the next transform given the last one applied
+    xaos_distributions: Option<Vec<FlameDistribution>>,
+    palette: Option<Palette>,
+
+    number_points: usize,
+    number_iterations: usize,
+    iteration_offset: usize,
+
+    seed: String,
+}
+
+impl HistogramGeneration for FlameAlgorithm {
+    fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
+        let reseeds = AtomicUsize::new(0);
+        let histogram = builder.par_accumulate(self.number_points, |worker, number_points| {
+            self.run_worker(worker, number_points, &builder, &reseeds)
+        });
+        info!(
+            "{} runaway points re-seeded in the viewport",
+            reseeds.into_inner()
+        );
+        histogram
+    }
+}
+
+impl FlameAlgorithm {
+    /// Run `number_points` walks with the random stream of the worker, in a private histogram.
+    /// The walks which run away are restarted from a new random point, counted in `reseeds`.
+    fn run_worker(
+        &self,
+        worker: usize,
+        number_points: usize,
+        builder: &HistogramBuilder,
+        reseeds: &AtomicUsize,
+    ) -> Histogram {
+        let mut rng: FlameRng = Seeder::from((self.seed.as_str(), worker)).make_rng();
+
+        let (x0, y0) = builder.point_top_left();
+        let uniform_distrib_x = Uniform::new(x0, x0 + builder.width_real());
+        let uniform_distrib_y = Uniform::new(y0, y0 + builder.height_real());
+        // Sample a new point in the window
+        let sample_point = |rng: &mut FlameRng| -> FlamePoint {
+            (
+                (rng.sample(uniform_distrib_x), rng.sample(uniform_distrib_y)),
+                rng.gen(),
+            )
+        };
+
+        let mut worker_reseeds = 0;
+        let mut histogram =
+            Histogram::new(builder.width_px, builder.height_px, builder.resolution_px);
+        let color_channels = self
+            .palette
+            .as_ref()
+            .map(|_| histogram.add_color_channels());
+        for _ in 0..number_points {
+            let mut point = sample_point(&mut rng);
+            let mut last_xform = None;
+            // Make a few iteration to make an offset
+            for _ in 0..self.iteration_offset {
+                point = self.one_round(point, &mut last_xform, &mut rng);
+                if is_runaway(point) {
+                    worker_reseeds += 1;
+                    point = sample_point(&mut rng);
+                    last_xform = None;
+                }
+            }
+
+            for _ in 0..self.number_iterations {
+                let plotted = match &self.final_xform {
+                    Some(final_xform) => final_xform.apply(point, &mut rng),
+                    None => point,
+                };
+                if !is_runaway(plotted) {
+                    self.add_point_to_histogram(plotted, &mut histogram, builder, color_channels);
+                }
+                point = self.one_round(point, &mut last_xform, &mut rng);
+                if is_runaway(point) {
+                    worker_reseeds += 1;
+                    point = sample_point(&mut rng);
+                    last_xform = None;
+                }
+            }
+        }
+        reseeds.fetch_add(worker_reseeds, Ordering::Relaxed);
+        histogram
+    }
+
+    /// Apply a random transform, picked according to the last one applied if there is xaos
+    fn one_round(
+        &self,
+        point: FlamePoint,
+        last_xform: &mut Option<usize>,
+        rng: &mut FlameRng,
+    ) -> FlamePoint {
+        let distribution = match (&self.xaos_distributions, *last_xform) {
+            (Some(distributions), Some(last)) => &distributions[last],
+            _ => &self.flame_distribution,
+        };
+        let index = rng.sample(distribution);
+        *last_xform = Some(index);
+        self.xforms[index].apply(point, rng)
+    }
+
+    fn add_point_to_histogram(
+        &self,
+        point: FlamePoint,
+        histogram: &mut Histogram,
+        builder: &HistogramBuilder,
+        color_channels: Option<[usize; 3]>,
+    ) {
+        let point_px = builder.real_to_pixel(point.0 .0, point.0 .1);
+        if let Some((x, y)) = point_px {
+            let (mut freq, mut color) = histogram.get_cell(x as usize, y as usize);
+            freq += 1.;
+            // Running mean of the colors of the points
+            color += (point.1 - color) / freq;
+
+            histogram.set_cell(x, y, (freq, color));
+
+            if let (Some(palette), Some(channels)) = (&self.palette, color_channels) {
+                histogram.add_color(channels, x, y, palette.color(point.1));
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Palettes mapping the color of the flame points, from 0 to 1, to RGB colors.
+
+use std::fs;
+use std::io;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::rendering::RgbColor;
+
+/// Number of colors of a palette, as in flam3
+pub const PALETTE_SIZE: usize = 256;
+
+/// Built-in palettes, as gradients between a few colors
+const NAMED_PALETTES: &[(&str, &[RgbColor])] = &[
+    ("greyscale", &[(0, 0, 0), (255, 255, 255)]),
+    (
+        "fire",
+        &[
+            (0, 0, 0),
+            (128, 0, 0),
+            (230, 70, 0),
+            (255, 190, 30),
+            (255, 255, 220),
+        ],
+    ),
+    (
+        "ocean",
+        &[
+            (0, 5, 40),
+            (0, 50, 130),
+            (0, 140, 200),
+            (90, 220, 230),
+            (230, 255, 255),
+        ],
+    ),
+    (
+        "forest",
+        &[(10, 30, 10), (30, 100, 30), (120, 170, 40), (210, 200, 120)],
+    ),
+    (
+        "sunset",
+        &[(40, 0, 70), (170, 20, 120), (250, 90, 60), (255, 200, 60)],
+    ),
+    ("ice", &[(255, 255, 255), (150, 210, 255), (20, 60, 200)]),
+    (
+        "rainbow",
+        &[
+            (255, 0, 0),
+            (255, 255, 0),
+            (0, 255, 0),
+            (0, 255, 255),
+            (0, 0, 255),
+            (255, 0, 255),
+            (255, 0, 0),
+        ],
+    ),
+];
+
+#[derive(Serialize, Deserialize)]
+pub enum PaletteSource {
+    /// Colors given inline. If there are less than 256 of them, they are interpolated.
+    Colors(Vec<RgbColor>),
+    /// Fractint map file, with the red, green and blue components of one color per line
+    File(String),
+    /// One of the built-in palettes: greyscale, fire, ocean, forest, sunset, ice or rainbow
+    Named(String),
+}
+
+impl PaletteSource {
+    /// Colors of the palette, before their interpolation
+    fn colors(&self) -> io::Result<Vec<RgbColor>> {
+        let colors = match self {
+            PaletteSource::Colors(colors) => colors.clone(),
+            PaletteSource::File(filename) => parse_map(&fs::read_to_string(filename)?)?,
+            PaletteSource::Named(name) => match NAMED_PALETTES
+                .iter()
+                .find(|(palette_name, _)| palette_name == name)
+            {
+                Some((_, colors)) => colors.to_vec(),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("unknown palette {}", name),
+                    ))
+                }
+            },
+        };
+        if colors.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "empty palette".to_string(),
+            ));
+        }
+        Ok(colors)
+    }
+
+    /// The 256 colors of the palette, linearly interpolated between the given ones, with channels
+    /// between 0 and 1
+    pub fn interpolated_colors(&self) -> io::Result<Vec<[f64; 3]>> {
+        let colors = self.colors()?;
+        let last = (colors.len() - 1) as f64;
+        Ok((0..PALETTE_SIZE)
+            .map(|index| {
+                let position = index as f64 / (PALETTE_SIZE - 1) as f64 * last;
+                let (before, after) = (
+                    colors[position.floor() as usize],
+                    colors[position.ceil() as usize],
+                );
+                let blend = position.fract();
+                let channel = |before: u8, after: u8| {
+                    (before as f64 * (1. - blend) + after as f64 * blend) / 255.
+                };
+                [
+                    channel(before.0, after.0),
+                    channel(before.1, after.1),
+                    channel(before.2, after.2),
+                ]
+            })
+            .collect())
+    }
+}
+
+/// Colors of a Fractint map file, the text after the three components of a line being ignored
+fn parse_map(map: &str) -> io::Result<Vec<RgbColor>> {
+    map.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let components = line
+                .split_whitespace()
+                .take(3)
+                .map(|component| component.parse::<u8>())
+                .collect::<Result<Vec<u8>, _>>();
+            match components {
+                Ok(components) if components.len() == 3 => {
+                    Ok((components[0], components[1], components[2]))
+                }
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid color {} in the palette", line),
+                )),
+            }
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PaletteConf {
+    pub source: PaletteSource,
+    /// Rotation of the hue of every color, in turns
+    #[serde(default)]
+    pub hue_rotation: f64,
+}
+
+impl PaletteConf {
+    pub fn build(&self) -> io::Result<Palette> {
+        let colors = self
+            .source
+            .interpolated_colors()?
+            .into_iter()
+            .map(|rgb| rotate_hue(rgb, self.hue_rotation))
+            .collect();
+        Ok(Palette { colors })
+    }
+}
+
+/// Rotate the hue of a color, whose channels are between 0 and 1, keeping its saturation and value
+fn rotate_hue([r, g, b]: [f64; 3], rotation: f64) -> [f64; 3] {
+    if rotation == 0. {
+        return [r, g, b];
+    }
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    if chroma == 0. {
+        return [r, g, b];
+    }
+    // Hue in sixths of turn
+    let hue = if max == r {
+        (g - b) / chroma
+    } else if max == g {
+        (b - r) / chroma + 2.
+    } else {
+        (r - g) / chroma + 4.
+    };
+    let hue = (hue + 6. * rotation).rem_euclid(6.);
+
+    let intermediate = chroma * (1. - (hue % 2. - 1.).abs());
+    let (r, g, b) = match hue as usize {
+        0 => (chroma, intermediate, 0.),
+        1 => (intermediate, chroma, 0.),
+        2 => (0., chroma, intermediate),
+        3 => (0., intermediate, chroma),
+        4 => (intermediate, 0., chroma),
+        _ => (chroma, 0., intermediate),
+    };
+    [r + min, g + min, b + min]
+}
+
+pub struct Palette {
+    colors: Vec<[f64; 3]>,
+}
+
+impl Palette {
+    /// Color of the palette, with channels between 0 and 1, for a point color between 0 and 1
+    pub fn color(&self, index: f64) -> [f64; 3] {
+        let index = (index.clamp(0., 1.) * (PALETTE_SIZE - 1) as f64).round() as usize;
+        self.colors[index]
+    }
+}
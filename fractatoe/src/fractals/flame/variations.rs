@@ -0,0 +1,565 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+use serde_derive::{Deserialize, Serialize};
+
+use super::{CoefFlame, FlameRng};
+
+/// Unweighted image of a point by a variation. The parameters, weight and affine coefficients of
+/// the variation are captured when it is built.
+pub type FlameFunction = Box<dyn Fn(f64, f64, &mut FlameRng) -> (f64, f64) + Send + Sync>;
+
+/// Avoids the divisions by 0
+const EPS: f64 = 1e-10;
+
+/// Variations of the fractal flame algorithm, numbered as in the paper of Draves and Reckase. The
+/// angles are the ones of the paper: theta = atan(x/y) and phi = atan(y/x).
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub enum VariationFunction {
+    /// Same as `Sinusoidal`
+    Bisin,
+    Linear,
+    Sinusoidal,
+    Spherical,
+    Swirl,
+    Horseshoe,
+    Polar,
+    Handkerchief,
+    Heart,
+    Disc,
+    Spiral,
+    Hyperbolic,
+    Diamond,
+    Ex,
+    /// Square root of angle atan(x/y) / 2, as in the first flames of fractatoe. Unlike the flam3
+    /// julia, it always takes the same root and folds the angles onto a half-plane: see
+    /// `Flam3Julia`. Not exported to flam3.
+    Julia,
+    /// One of the two square roots, at random, as the julia variation of flam3
+    Flam3Julia,
+    Bent,
+    /// Depends on the affine coefficients b, c, e and f
+    Waves,
+    Fisheye,
+    /// Depends on the affine coefficients c and f
+    Popcorn,
+    Exponential,
+    Power,
+    Cosine,
+    /// Depends on the affine coefficient c
+    Rings,
+    /// Depends on the affine coefficients c and f
+    Fan,
+    Blob {
+        high: f64,
+        low: f64,
+        waves: f64,
+    },
+    Pdj {
+        a: f64,
+        b: f64,
+        c: f64,
+        d: f64,
+    },
+    Fan2 {
+        x: f64,
+        y: f64,
+    },
+    Rings2 {
+        val: f64,
+    },
+    Eyefish,
+    Bubble,
+    Cylinder,
+    Perspective {
+        angle: f64,
+        dist: f64,
+    },
+    Noise,
+    Julian {
+        power: f64,
+        dist: f64,
+    },
+    Juliascope {
+        power: f64,
+        dist: f64,
+    },
+    Blur,
+    GaussianBlur,
+    RadialBlur {
+        angle: f64,
+    },
+    Pie {
+        slices: f64,
+        rotation: f64,
+        thickness: f64,
+    },
+    Ngon {
+        power: f64,
+        sides: f64,
+        corners: f64,
+        circle: f64,
+    },
+    Curl {
+        c1: f64,
+        c2: f64,
+    },
+    Rectangles {
+        x: f64,
+        y: f64,
+    },
+    Arch,
+    Tangent,
+    Square,
+    Rays,
+    Blade,
+    Secant2,
+    Twintrian,
+    Cross,
+}
+
+fn radius(x: f64, y: f64) -> f64 {
+    (x * x + y * y).sqrt()
+}
+
+/// Angle of the paper, measured from the y axis
+fn theta(x: f64, y: f64) -> f64 {
+    x.atan2(y)
+}
+
+/// Usual angle, measured from the x axis
+fn phi(x: f64, y: f64) -> f64 {
+    y.atan2(x)
+}
+
+fn linear(x: f64, y: f64) -> (f64, f64) {
+    (x, y)
+}
+
+fn sinusoidal(x: f64, y: f64) -> (f64, f64) {
+    (x.sin(), y.sin())
+}
+
+fn spherical(x: f64, y: f64) -> (f64, f64) {
+    let norm = x * x + y * y;
+    (x / norm, y / norm)
+}
+
+fn swirl(x: f64, y: f64) -> (f64, f64) {
+    let r_sq: f64 = x * x + y * y;
+    (
+        x * (r_sq).sin() - y * (r_sq).cos(),
+        x * (r_sq).cos() + y * (r_sq).sin(),
+    )
+}
+
+fn horseshoe(x: f64, y: f64) -> (f64, f64) {
+    let r = radius(x, y) + EPS;
+    ((x - y) * (x + y) / r, 2. * x * y / r)
+}
+
+fn polar(x: f64, y: f64) -> (f64, f64) {
+    (theta(x, y) / PI, radius(x, y) - 1.)
+}
+
+fn handkerchief(x: f64, y: f64) -> (f64, f64) {
+    let (r, theta) = (radius(x, y), theta(x, y));
+    (r * (theta + r).sin(), r * (theta - r).cos())
+}
+
+fn heart(x: f64, y: f64) -> (f64, f64) {
+    let r = radius(x, y);
+    let theta_r = theta(x, y) * r;
+    (r * theta_r.sin(), -r * theta_r.cos())
+}
+
+fn disc(x: f64, y: f64) -> (f64, f64) {
+    let theta_pi = theta(x, y) / PI;
+    let pi_r = PI * radius(x, y);
+    (theta_pi * pi_r.sin(), theta_pi * pi_r.cos())
+}
+
+fn spiral(x: f64, y: f64) -> (f64, f64) {
+    let r = radius(x, y) + EPS;
+    // sin(theta) = x/r and cos(theta) = y/r
+    ((y / r + r.sin()) / r, (x / r - r.cos()) / r)
+}
+
+fn hyperbolic(x: f64, y: f64) -> (f64, f64) {
+    let r = radius(x, y) + EPS;
+    (x / r / r, r * (y / r))
+}
+
+fn diamond(x: f64, y: f64) -> (f64, f64) {
+    let r = radius(x, y) + EPS;
+    (x / r * r.cos(), y / r * r.sin())
+}
+
+fn ex(x: f64, y: f64) -> (f64, f64) {
+    let (r, theta) = (radius(x, y), theta(x, y));
+    let p0 = (theta + r).sin().powi(3);
+    let p1 = (theta - r).cos().powi(3);
+    (r * (p0 + p1), r * (p0 - p1))
+}
+
+fn julia(x: f64, y: f64) -> (f64, f64) {
+    let theta: f64 = (x / y).atan();
+    let sqrt_r = (x * x + y * y).sqrt().sqrt();
+    (sqrt_r * (theta / 2.).cos(), sqrt_r * (theta / 2.).sin())
+}
+
+fn flam3_julia(x: f64, y: f64, rng: &mut FlameRng) -> (f64, f64) {
+    let mut angle = theta(x, y) / 2.;
+    if rng.gen() {
+        angle += PI;
+    }
+    let sqrt_r = radius(x, y).sqrt();
+    (sqrt_r * angle.cos(), sqrt_r * angle.sin())
+}
+
+fn bent(x: f64, y: f64) -> (f64, f64) {
+    (
+        if x < 0. { 2. * x } else { x },
+        if y < 0. { y / 2. } else { y },
+    )
+}
+
+fn waves(x: f64, y: f64, coefs: CoefFlame) -> (f64, f64) {
+    (
+        x + coefs.1 * (y / (coefs.2 * coefs.2 + EPS)).sin(),
+        y + coefs.4 * (x / (coefs.5 * coefs.5 + EPS)).sin(),
+    )
+}
+
+fn fisheye(x: f64, y: f64) -> (f64, f64) {
+    let r = 2. / (radius(x, y) + 1.);
+    (r * y, r * x)
+}
+
+fn popcorn(x: f64, y: f64, coefs: CoefFlame) -> (f64, f64) {
+    (
+        x + coefs.2 * (3. * y).tan().sin(),
+        y + coefs.5 * (3. * x).tan().sin(),
+    )
+}
+
+fn exponential(x: f64, y: f64) -> (f64, f64) {
+    let exp = (x - 1.).exp();
+    let angle = PI * y;
+    (exp * angle.cos(), exp * angle.sin())
+}
+
+fn power(x: f64, y: f64) -> (f64, f64) {
+    let r = radius(x, y) + EPS;
+    let (sin, cos) = (x / r, y / r);
+    let r = r.powf(sin);
+    (r * cos, r * sin)
+}
+
+fn cosine(x: f64, y: f64) -> (f64, f64) {
+    let angle = x * PI;
+    (angle.cos() * y.cosh(), -angle.sin() * y.sinh())
+}
+
+fn rings(x: f64, y: f64, coefs: CoefFlame) -> (f64, f64) {
+    let dx = coefs.2 * coefs.2 + EPS;
+    let r = radius(x, y) + EPS;
+    let (sin, cos) = (x / r, y / r);
+    let r = (r + dx) % (2. * dx) - dx + r * (1. - dx);
+    (r * cos, r * sin)
+}
+
+fn fan(x: f64, y: f64, coefs: CoefFlame) -> (f64, f64) {
+    let dx = PI * (coefs.2 * coefs.2 + EPS);
+    let (r, mut angle) = (radius(x, y), theta(x, y));
+    if (angle + coefs.5) % dx > dx / 2. {
+        angle -= dx / 2.;
+    } else {
+        angle += dx / 2.;
+    }
+    (r * angle.cos(), r * angle.sin())
+}
+
+fn blob(x: f64, y: f64, high: f64, low: f64, waves: f64) -> (f64, f64) {
+    let r = radius(x, y) + EPS;
+    let (sin, cos) = (x / r, y / r);
+    let r = r * (low + (high - low) * (0.5 + 0.5 * (waves * theta(x, y)).sin()));
+    (r * sin, r * cos)
+}
+
+fn fan2(x: f64, y: f64, fan_x: f64, fan_y: f64) -> (f64, f64) {
+    let dx = PI * (fan_x * fan_x + EPS);
+    let (r, mut angle) = (radius(x, y), theta(x, y));
+    let t = angle + fan_y - dx * ((angle + fan_y) / dx).trunc();
+    if t > dx / 2. {
+        angle -= dx / 2.;
+    } else {
+        angle += dx / 2.;
+    }
+    (r * angle.sin(), r * angle.cos())
+}
+
+fn rings2(x: f64, y: f64, val: f64) -> (f64, f64) {
+    let dx = val * val + EPS;
+    let r = radius(x, y) + EPS;
+    let (sin, cos) = (x / r, y / r);
+    let r = r - 2. * dx * ((r + dx) / (2. * dx)).trunc() + r * (1. - dx);
+    (r * sin, r * cos)
+}
+
+fn eyefish(x: f64, y: f64) -> (f64, f64) {
+    let r = 2. / (radius(x, y) + 1.);
+    (r * x, r * y)
+}
+
+fn bubble(x: f64, y: f64) -> (f64, f64) {
+    let r = 4. / (x * x + y * y + 4.);
+    (r * x, r * y)
+}
+
+fn cylinder(x: f64, y: f64) -> (f64, f64) {
+    (x.sin(), y)
+}
+
+fn perspective(x: f64, y: f64, angle: f64, dist: f64) -> (f64, f64) {
+    let angle = angle * PI / 2.;
+    let t = 1. / (dist - y * angle.sin());
+    (dist * x * t, dist * angle.cos() * y * t)
+}
+
+fn noise(x: f64, y: f64, rng: &mut FlameRng) -> (f64, f64) {
+    let angle = rng.gen::<f64>() * 2. * PI;
+    let r: f64 = rng.gen();
+    (x * r * angle.cos(), y * r * angle.sin())
+}
+
+fn julian(x: f64, y: f64, power: f64, dist: f64, rng: &mut FlameRng) -> (f64, f64) {
+    let root = (power.abs() * rng.gen::<f64>()).trunc();
+    let angle = (phi(x, y) + 2. * PI * root) / power;
+    let r = (x * x + y * y).powf(dist / power / 2.);
+    (r * angle.cos(), r * angle.sin())
+}
+
+fn juliascope(x: f64, y: f64, power: f64, dist: f64, rng: &mut FlameRng) -> (f64, f64) {
+    let root = (power.abs() * rng.gen::<f64>()).trunc();
+    let angle = if root % 2. == 0. {
+        (2. * PI * root + phi(x, y)) / power
+    } else {
+        (2. * PI * root - phi(x, y)) / power
+    };
+    let r = (x * x + y * y).powf(dist / power / 2.);
+    (r * angle.cos(), r * angle.sin())
+}
+
+fn blur(rng: &mut FlameRng) -> (f64, f64) {
+    let angle = rng.gen::<f64>() * 2. * PI;
+    let r: f64 = rng.gen();
+    (r * angle.cos(), r * angle.sin())
+}
+
+/// Sum of 4 uniform variables minus 2, a cheap approximation of a gaussian variable
+fn gaussian_sample(rng: &mut FlameRng) -> f64 {
+    (0..4).map(|_| rng.gen::<f64>()).sum::<f64>() - 2.
+}
+
+fn gaussian_blur(rng: &mut FlameRng) -> (f64, f64) {
+    let angle = rng.gen::<f64>() * 2. * PI;
+    let r = gaussian_sample(rng);
+    (r * angle.cos(), r * angle.sin())
+}
+
+/// Unlike the other variations, the blur depends on the weight and the result is not scaled by it
+fn radial_blur(x: f64, y: f64, angle: f64, weight: f64, rng: &mut FlameRng) -> (f64, f64) {
+    let angle = angle * PI / 2.;
+    let gaussian = weight * gaussian_sample(rng);
+    let blurred_angle = phi(x, y) + angle.sin() * gaussian;
+    let zoom = angle.cos() * gaussian - 1.;
+    let r = radius(x, y);
+    (
+        r * blurred_angle.cos() + zoom * x,
+        r * blurred_angle.sin() + zoom * y,
+    )
+}
+
+fn pie(slices: f64, rotation: f64, thickness: f64, rng: &mut FlameRng) -> (f64, f64) {
+    let slice = (rng.gen::<f64>() * slices + 0.5).trunc();
+    let angle = rotation + 2. * PI * (slice + rng.gen::<f64>() * thickness) / slices;
+    let r: f64 = rng.gen();
+    (r * angle.cos(), r * angle.sin())
+}
+
+fn ngon(x: f64, y: f64, power: f64, sides: f64, corners: f64, circle: f64) -> (f64, f64) {
+    let r_factor = (x * x + y * y).powf(power / 2.);
+    let side_angle = 2. * PI / sides;
+    let angle = phi(x, y);
+    let mut angle = angle - side_angle * (angle / side_angle).floor();
+    if angle > side_angle / 2. {
+        angle -= side_angle;
+    }
+    let amplitude = (corners * (1. / (angle.cos() + EPS) - 1.) + circle) / (r_factor + EPS);
+    (x * amplitude, y * amplitude)
+}
+
+fn curl(x: f64, y: f64, c1: f64, c2: f64) -> (f64, f64) {
+    let re = 1. + c1 * x + c2 * (x * x - y * y);
+    let im = c1 * y + 2. * c2 * x * y;
+    let r = 1. / (re * re + im * im);
+    ((x * re + y * im) * r, (y * re - x * im) * r)
+}
+
+fn rectangles(x: f64, y: f64, rectangle_x: f64, rectangle_y: f64) -> (f64, f64) {
+    let fold = |value: f64, size: f64| {
+        if size == 0. {
+            value
+        } else {
+            (2. * (value / size).floor() + 1.) * size - value
+        }
+    };
+    (fold(x, rectangle_x), fold(y, rectangle_y))
+}
+
+fn arch(weight: f64, rng: &mut FlameRng) -> (f64, f64) {
+    let angle = rng.gen::<f64>() * weight * PI;
+    let (sin, cos) = angle.sin_cos();
+    (sin, sin * sin / cos)
+}
+
+fn tangent(x: f64, y: f64) -> (f64, f64) {
+    (x.sin() / y.cos(), y.tan())
+}
+
+fn square(rng: &mut FlameRng) -> (f64, f64) {
+    (rng.gen::<f64>() - 0.5, rng.gen::<f64>() - 0.5)
+}
+
+/// Unlike the other variations, the result is not scaled by the weight
+fn rays(x: f64, y: f64, weight: f64, rng: &mut FlameRng) -> (f64, f64) {
+    let angle = weight * rng.gen::<f64>() * PI;
+    let r = weight / (x * x + y * y + EPS);
+    let tan_r = weight * angle.tan() * r;
+    (tan_r * x.cos(), tan_r * y.sin())
+}
+
+fn blade(x: f64, y: f64, weight: f64, rng: &mut FlameRng) -> (f64, f64) {
+    let r = rng.gen::<f64>() * weight * radius(x, y);
+    let (sin, cos) = r.sin_cos();
+    (x * (cos + sin), x * (cos - sin))
+}
+
+fn secant2(x: f64, y: f64, weight: f64) -> (f64, f64) {
+    let cos = (weight * radius(x, y)).cos();
+    if cos < 0. {
+        (x, 1. / cos + 1.)
+    } else {
+        (x, 1. / cos - 1.)
+    }
+}
+
+fn twintrian(x: f64, y: f64, weight: f64, rng: &mut FlameRng) -> (f64, f64) {
+    let r = rng.gen::<f64>() * weight * radius(x, y);
+    let (sin, cos) = r.sin_cos();
+    let mut diff = (sin * sin).log10() + cos;
+    if !diff.is_finite() {
+        diff = -30.;
+    }
+    (x * diff, x * (diff - sin * PI))
+}
+
+fn cross(x: f64, y: f64) -> (f64, f64) {
+    let s = x * x - y * y;
+    let r = (1. / (s * s + EPS)).sqrt();
+    (x * r, y * r)
+}
+
+/// Variation which ignores the random stream
+fn deterministic(function: fn(f64, f64) -> (f64, f64)) -> FlameFunction {
+    box move |x, y, _| function(x, y)
+}
+
+impl VariationFunction {
+    pub fn build(self, weight: f64, coefs: CoefFlame) -> FlameFunction {
+        match self {
+            VariationFunction::Bisin => deterministic(sinusoidal),
+            VariationFunction::Linear => deterministic(linear),
+            VariationFunction::Sinusoidal => deterministic(sinusoidal),
+            VariationFunction::Spherical => deterministic(spherical),
+            VariationFunction::Swirl => deterministic(swirl),
+            VariationFunction::Horseshoe => deterministic(horseshoe),
+            VariationFunction::Polar => deterministic(polar),
+            VariationFunction::Handkerchief => deterministic(handkerchief),
+            VariationFunction::Heart => deterministic(heart),
+            VariationFunction::Disc => deterministic(disc),
+            VariationFunction::Spiral => deterministic(spiral),
+            VariationFunction::Hyperbolic => deterministic(hyperbolic),
+            VariationFunction::Diamond => deterministic(diamond),
+            VariationFunction::Ex => deterministic(ex),
+            VariationFunction::Julia => deterministic(julia),
+            VariationFunction::Flam3Julia => box flam3_julia,
+            VariationFunction::Bent => deterministic(bent),
+            VariationFunction::Waves => box move |x, y, _| waves(x, y, coefs),
+            VariationFunction::Fisheye => deterministic(fisheye),
+            VariationFunction::Popcorn => box move |x, y, _| popcorn(x, y, coefs),
+            VariationFunction::Exponential => deterministic(exponential),
+            VariationFunction::Power => deterministic(power),
+            VariationFunction::Cosine => deterministic(cosine),
+            VariationFunction::Rings => box move |x, y, _| rings(x, y, coefs),
+            VariationFunction::Fan => box move |x, y, _| fan(x, y, coefs),
+            VariationFunction::Blob { high, low, waves } => {
+                box move |x, y, _| blob(x, y, high, low, waves)
+            }
+            VariationFunction::Pdj { a, b, c, d } => box move |x, y, _| {
+                ((a * y).sin() - (b * x).cos(), (c * x).sin() - (d * y).cos())
+            },
+            VariationFunction::Fan2 { x: fan_x, y: fan_y } => {
+                box move |x, y, _| fan2(x, y, fan_x, fan_y)
+            }
+            VariationFunction::Rings2 { val } => box move |x, y, _| rings2(x, y, val),
+            VariationFunction::Eyefish => deterministic(eyefish),
+            VariationFunction::Bubble => deterministic(bubble),
+            VariationFunction::Cylinder => deterministic(cylinder),
+            VariationFunction::Perspective { angle, dist } => {
+                box move |x, y, _| perspective(x, y, angle, dist)
+            }
+            VariationFunction::Noise => box noise,
+            VariationFunction::Julian { power, dist } => {
+                box move |x, y, rng| julian(x, y, power, dist, rng)
+            }
+            VariationFunction::Juliascope { power, dist } => {
+                box move |x, y, rng| juliascope(x, y, power, dist, rng)
+            }
+            VariationFunction::Blur => box |_, _, rng| blur(rng),
+            VariationFunction::GaussianBlur => box |_, _, rng| gaussian_blur(rng),
+            VariationFunction::RadialBlur { angle } => box move |x, y, rng| {
+                let (x_blur, y_blur) = radial_blur(x, y, angle, weight, rng);
+                (x_blur / weight, y_blur / weight)
+            },
+            VariationFunction::Pie {
+                slices,
+                rotation,
+                thickness,
+            } => box move |_, _, rng| pie(slices, rotation, thickness, rng),
+            VariationFunction::Ngon {
+                power,
+                sides,
+                corners,
+                circle,
+            } => box move |x, y, _| ngon(x, y, power, sides, corners, circle),
+            VariationFunction::Curl { c1, c2 } => box move |x, y, _| curl(x, y, c1, c2),
+            VariationFunction::Rectangles {
+                x: rectangle_x,
+                y: rectangle_y,
+            } => box move |x, y, _| rectangles(x, y, rectangle_x, rectangle_y),
+            VariationFunction::Arch => box move |_, _, rng| arch(weight, rng),
+            VariationFunction::Tangent => deterministic(tangent),
+            VariationFunction::Square => box |_, _, rng| square(rng),
+            VariationFunction::Rays => box move |x, y, rng| {
+                let (x_rays, y_rays) = rays(x, y, weight, rng);
+                (x_rays / weight, y_rays / weight)
+            },
+            VariationFunction::Blade => box move |x, y, rng| blade(x, y, weight, rng),
+            VariationFunction::Secant2 => box move |x, y, _| secant2(x, y, weight),
+            VariationFunction::Twintrian => box move |x, y, rng| twintrian(x, y, weight, rng),
+            VariationFunction::Cross => deterministic(cross),
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use num::complex::Complex;
+
+type C64 = Complex<f64>;
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    /// Byte offset in the expression where the error was found
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at position {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Exp,
+    Log,
+    Sqrt,
+    Abs,
+    Conj,
+    Re,
+    Im,
+    Arg,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        Some(match name {
+            "sin" => Function::Sin,
+            "cos" => Function::Cos,
+            "tan" => Function::Tan,
+            "sinh" => Function::Sinh,
+            "cosh" => Function::Cosh,
+            "tanh" => Function::Tanh,
+            "exp" => Function::Exp,
+            "log" | "ln" => Function::Log,
+            "sqrt" => Function::Sqrt,
+            "abs" => Function::Abs,
+            "conj" => Function::Conj,
+            "re" => Function::Re,
+            "im" => Function::Im,
+            "arg" => Function::Arg,
+            _ => return None,
+        })
+    }
+
+    fn apply(self, z: C64) -> C64 {
+        match self {
+            Function::Sin => z.sin(),
+            Function::Cos => z.cos(),
+            Function::Tan => z.tan(),
+            Function::Sinh => z.sinh(),
+            Function::Cosh => z.cosh(),
+            Function::Tanh => z.tanh(),
+            Function::Exp => z.exp(),
+            Function::Log => z.ln(),
+            Function::Sqrt => z.sqrt(),
+            Function::Abs => Complex::new(z.norm(), 0.),
+            Function::Conj => z.conj(),
+            Function::Re => Complex::new(z.re, 0.),
+            Function::Im => Complex::new(z.im, 0.),
+            Function::Arg => Complex::new(z.arg(), 0.),
+        }
+    }
+}
+
+/// Instruction of the compiled expression, evaluated on a stack
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Const(C64),
+    Z,
+    C,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    PowI(i32),
+    Neg,
+    Call(Function),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(usize, usize),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    LeftParen,
+    RightParen,
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let bytes = source.as_bytes();
+    let mut tokens = vec![];
+    let mut position = 0;
+    while position < bytes.len() {
+        let start = position;
+        let character = bytes[position] as char;
+        let token = match character {
+            ' ' | '\t' | '\n' | '\r' => {
+                position += 1;
+                continue;
+            }
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '^' => Token::Caret,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '0'..='9' | '.' => {
+                while position < bytes.len()
+                    && (bytes[position].is_ascii_digit() || bytes[position] == b'.')
+                {
+                    position += 1;
+                }
+                // Exponent part
+                if position < bytes.len() && (bytes[position] == b'e' || bytes[position] == b'E') {
+                    let mut exponent_end = position + 1;
+                    if exponent_end < bytes.len()
+                        && (bytes[exponent_end] == b'+' || bytes[exponent_end] == b'-')
+                    {
+                        exponent_end += 1;
+                    }
+                    if exponent_end < bytes.len() && bytes[exponent_end].is_ascii_digit() {
+                        position = exponent_end;
+                        while position < bytes.len() && bytes[position].is_ascii_digit() {
+                            position += 1;
+                        }
+                    }
+                }
+                let number = source[start..position].parse().map_err(|_| ParseError {
+                    position: start,
+                    message: format!("invalid number {}", &source[start..position]),
+                })?;
+                tokens.push((start, Token::Number(number)));
+                continue;
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                while position < bytes.len()
+                    && (bytes[position].is_ascii_alphanumeric() || bytes[position] == b'_')
+                {
+                    position += 1;
+                }
+                tokens.push((start, Token::Identifier(start, position)));
+                continue;
+            }
+            c => {
+                return Err(ParseError {
+                    position: start,
+                    message: format!("unexpected character {}", c),
+                })
+            }
+        };
+        tokens.push((start, token));
+        position += 1;
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser emitting the operations in postfix order
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(usize, Token)>,
+    current: usize,
+    parameters: &'a BTreeMap<String, (f64, f64)>,
+    ops: Vec<Op>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.current).map(|(_, token)| *token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.current)
+            .map(|(position, _)| *position)
+            .unwrap_or_else(|| self.source.len())
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, ParseError> {
+        Err(ParseError {
+            position: self.position(),
+            message: message.to_string(),
+        })
+    }
+
+    fn expect(&mut self, expected: Token, message: &str) -> Result<(), ParseError> {
+        if self.peek() == Some(expected) {
+            self.current += 1;
+            Ok(())
+        } else {
+            self.error(message)
+        }
+    }
+
+    // expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<(), ParseError> {
+        self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => Op::Add,
+                Some(Token::Minus) => Op::Sub,
+                _ => return Ok(()),
+            };
+            self.current += 1;
+            self.term()?;
+            self.ops.push(op);
+        }
+    }
+
+    // term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<(), ParseError> {
+        self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => Op::Mul,
+                Some(Token::Slash) => Op::Div,
+                _ => return Ok(()),
+            };
+            self.current += 1;
+            self.unary()?;
+            self.ops.push(op);
+        }
+    }
+
+    // unary := '-' unary | power
+    fn unary(&mut self) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(Token::Minus) => {
+                self.current += 1;
+                self.unary()?;
+                self.ops.push(Op::Neg);
+                Ok(())
+            }
+            Some(Token::Plus) => {
+                self.current += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    // power := primary ('^' unary)?, right associative
+    fn power(&mut self) -> Result<(), ParseError> {
+        self.primary()?;
+        if self.peek() == Some(Token::Caret) {
+            self.current += 1;
+            let exponent_start = self.ops.len();
+            self.unary()?;
+            // Integer exponents are much faster and more accurate with repeated multiplications
+            match self.ops[exponent_start..] {
+                [Op::Const(exponent)]
+                    if exponent.im == 0.
+                        && exponent.re.fract() == 0.
+                        && exponent.re.abs() <= i32::MAX as f64 =>
+                {
+                    self.ops.truncate(exponent_start);
+                    self.ops.push(Op::PowI(exponent.re as i32));
+                }
+                _ => self.ops.push(Op::Pow),
+            }
+        }
+        Ok(())
+    }
+
+    // primary := number | identifier | function '(' expression ')' | '(' expression ')'
+    fn primary(&mut self) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(Token::Number(number)) => {
+                self.current += 1;
+                self.ops.push(Op::Const(Complex::new(number, 0.)));
+                Ok(())
+            }
+            Some(Token::LeftParen) => {
+                self.current += 1;
+                self.expression()?;
+                self.expect(Token::RightParen, "expected )")
+            }
+            Some(Token::Identifier(start, end)) => {
+                let name = &self.source[start..end];
+                if let Some(function) = Function::from_name(name) {
+                    self.current += 1;
+                    self.expect(Token::LeftParen, "expected ( after a function name")?;
+                    self.expression()?;
+                    self.expect(Token::RightParen, "expected )")?;
+                    self.ops.push(Op::Call(function));
+                    return Ok(());
+                }
+                let op = match name {
+                    "z" => Op::Z,
+                    "c" => Op::C,
+                    "i" => Op::Const(Complex::i()),
+                    "pi" => Op::Const(Complex::new(std::f64::consts::PI, 0.)),
+                    "e" => Op::Const(Complex::new(std::f64::consts::E, 0.)),
+                    _ => match self.parameters.get(name) {
+                        Some(&(re, im)) => Op::Const(Complex::new(re, im)),
+                        None => return self.error(&format!("unknown identifier {}", name)),
+                    },
+                };
+                self.current += 1;
+                self.ops.push(op);
+                Ok(())
+            }
+            Some(_) => self.error("expected a number, a variable or a parenthesis"),
+            None => self.error("unexpected end of the expression"),
+        }
+    }
+}
+
+/// Complex expression of `z`, `c` and constant parameters, compiled once into a sequence of stack
+/// operations
+#[derive(Clone, Debug)]
+pub struct Expression {
+    ops: Vec<Op>,
+    stack_size: usize,
+}
+
+impl Expression {
+    pub fn parse(
+        source: &str,
+        parameters: &BTreeMap<String, (f64, f64)>,
+    ) -> Result<Expression, ParseError> {
+        let mut parser = Parser {
+            source,
+            tokens: tokenize(source)?,
+            current: 0,
+            parameters,
+            ops: vec![],
+        };
+        parser.expression()?;
+        if parser.current != parser.tokens.len() {
+            return parser.error("unexpected token");
+        }
+
+        // Maximal height of the stack during the evaluation
+        let (mut height, mut stack_size) = (0usize, 0usize);
+        for op in parser.ops.iter() {
+            match op {
+                Op::Const(_) | Op::Z | Op::C => height += 1,
+                Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Pow => height -= 1,
+                Op::PowI(_) | Op::Neg | Op::Call(_) => (),
+            }
+            stack_size = stack_size.max(height);
+        }
+
+        Ok(Expression {
+            ops: parser.ops,
+            stack_size,
+        })
+    }
+
+    /// A stack to be reused for the evaluations of this expression
+    pub fn new_stack(&self) -> Vec<C64> {
+        Vec::with_capacity(self.stack_size)
+    }
+
+    pub fn evaluate(&self, z: C64, c: C64, stack: &mut Vec<C64>) -> C64 {
+        stack.clear();
+        for op in self.ops.iter() {
+            let value = match *op {
+                Op::Const(value) => value,
+                Op::Z => z,
+                Op::C => c,
+                Op::PowI(exponent) => stack.pop().unwrap().powi(exponent),
+                Op::Neg => -stack.pop().unwrap(),
+                Op::Call(function) => function.apply(stack.pop().unwrap()),
+                Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Pow => {
+                    let right = stack.pop().unwrap();
+                    let left = stack.pop().unwrap();
+                    match op {
+                        Op::Add => left + right,
+                        Op::Sub => left - right,
+                        Op::Mul => left * right,
+                        Op::Div => left / right,
+                        _ => left.powc(right),
+                    }
+                }
+            };
+            stack.push(value);
+        }
+        stack[0]
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use num::complex::Complex;
+use serde_derive::{Deserialize, Serialize};
+
+use super::escape_time::{escape_time_histogram_from_escapes, Escape};
+use super::histogram::{Histogram, HistogramBuilder};
+use super::HistogramGeneration;
+
+pub mod expression;
+
+use expression::{Expression, ParseError};
+
+/// Condition ending the iteration of a point
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub enum Bailout {
+    /// |z| > bound
+    Modulus(f64),
+    /// |Re(z)| + |Im(z)| > bound
+    Manhattan(f64),
+    /// max(|Re(z)|, |Im(z)|) > bound
+    Square(f64),
+    /// |Re(z)| > bound
+    Real(f64),
+    /// |Im(z)| > bound
+    Imaginary(f64),
+    /// |z_(n+1) - z_n| < epsilon, for formulas converging to attracting points
+    Convergence(f64),
+}
+
+impl Bailout {
+    /// Whether the iteration ends at z, `z_previous` being absent for the starting value
+    fn is_reached(self, z: Complex<f64>, z_previous: Option<Complex<f64>>) -> bool {
+        match self {
+            Bailout::Modulus(bound) => z.norm() > bound,
+            Bailout::Manhattan(bound) => z.re.abs() + z.im.abs() > bound,
+            Bailout::Square(bound) => z.re.abs().max(z.im.abs()) > bound,
+            Bailout::Real(bound) => z.re.abs() > bound,
+            Bailout::Imaginary(bound) => z.im.abs() > bound,
+            Bailout::Convergence(epsilon) => match z_previous {
+                Some(z_previous) => (z - z_previous).norm() < epsilon,
+                None => false,
+            },
+        }
+    }
+}
+
+/// Escape-time fractal of a user-defined iteration z -> formula(z, c), e.g. "z^3 + c*sin(z)".
+///
+/// The formula may use `z`, `c`, the constants `i`, `pi` and `e`, the parameters, the operators
+/// `+ - * / ^` and the functions sin, cos, tan, sinh, cosh, tanh, exp, log, sqrt, abs, conj, re,
+/// im and arg.
+#[derive(Serialize, Deserialize)]
+pub struct FormulaConf {
+    formula: String,
+    /// Named complex constants usable in the formulas
+    #[serde(default)]
+    parameters: BTreeMap<String, (f64, f64)>,
+    /// Starting value of z as a formula of z (the point of the plane for a Julia set, 0
+    /// otherwise), c and the parameters, "z" if absent
+    initial_z: Option<String>,
+    /// Fixed value of c, in which case z starts at each point of the plane (Julia set)
+    julia_c: Option<(f64, f64)>,
+    bailout: Bailout,
+    iterations: usize,
+}
+
+impl FormulaConf {
+    pub fn build(self) -> Result<FormulaAlgorithm, ParseError> {
+        let formula = Expression::parse(&self.formula, &self.parameters)?;
+        let initial_z =
+            Expression::parse(self.initial_z.as_deref().unwrap_or("z"), &self.parameters)?;
+        Ok(FormulaAlgorithm {
+            formula,
+            initial_z,
+            julia_c: self.julia_c.map(|(re, im)| Complex::new(re, im)),
+            bailout: self.bailout,
+            iterations: self.iterations,
+        })
+    }
+}
+
+pub struct FormulaAlgorithm {
+    formula: Expression,
+    initial_z: Expression,
+    julia_c: Option<Complex<f64>>,
+    bailout: Bailout,
+    iterations: usize,
+}
+
+impl FormulaAlgorithm {
+    /// Escape of the point, the starting value being checked at the iteration 0 like for the
+    /// built-in fractals
+    fn divergence(&self, x: f64, y: f64, stack: &mut Vec<Complex<f64>>) -> Escape {
+        let point = Complex::new(x, y);
+        let (z, c) = match self.julia_c {
+            Some(c) => (point, c),
+            None => (Complex::new(0., 0.), point),
+        };
+        let mut z = self.initial_z.evaluate(z, c, stack);
+        let mut z_previous = None;
+
+        for i in 0..self.iterations {
+            if self.bailout.is_reached(z, z_previous) {
+                return Escape::Escaped(i as f64);
+            }
+            z_previous = Some(z);
+            z = self.formula.evaluate(z, c, stack);
+        }
+        Escape::Interior
+    }
+}
+
+impl HistogramGeneration for FormulaAlgorithm {
+    fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
+        // One evaluation stack per row, reused from one pixel to the next
+        let escapes = builder.par_map_pixels_init(
+            || self.formula.new_stack(),
+            |stack, (x_float, y_float)| (self.divergence(x_float, y_float, stack), 0., vec![]),
+        );
+        escape_time_histogram_from_escapes(&builder, &[], escapes)
+    }
+}
@@ -1,23 +1,173 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
 use serde_derive::{Deserialize, Serialize};
 
+use bigdecimal::{BigDecimal, FromPrimitive};
 use itertools::Itertools;
+use rayon::prelude::*;
 
 pub type Point = (f64, f64);
 
 pub type F64Color = f64;
 pub type HistogramCell = (f64, F64Color);
 
+/// Real coordinate given either as a number or as a decimal string of arbitrary precision, kept
+/// as is through serialization
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Coordinate {
+    Float(f64),
+    Decimal(String),
+}
+
+impl Coordinate {
+    /// Nearest f64 of the coordinate
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Coordinate::Float(value) => *value,
+            Coordinate::Decimal(value) => value.parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    pub fn to_big_decimal(&self) -> BigDecimal {
+        match self {
+            Coordinate::Float(value) => BigDecimal::from_f64(*value).unwrap(),
+            Coordinate::Decimal(value) => {
+                BigDecimal::from_str(value).expect("Coordinate validated on deserialization")
+            }
+        }
+    }
+}
+
+/// Serialized form of the histogram builder, with exact coordinates
 #[derive(Deserialize, Serialize)]
+struct HistogramBuilderConf {
+    width_px: usize,
+    height_px: usize,
+    resolution_px: usize,
+    center: (Coordinate, Coordinate),
+    width_real: Coordinate,
+    height_real: Coordinate,
+}
+
+/// Viewport of a histogram. The center and the real dimensions are given as f64 to the
+/// generators, the coordinates of the configuration being also available with arbitrary
+/// precision. The f64 values are only derived from the precise ones on construction, so that both
+/// stay in sync.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(try_from = "HistogramBuilderConf", into = "HistogramBuilderConf")]
 pub struct HistogramBuilder {
     pub width_px: usize,
     pub height_px: usize,
     pub resolution_px: usize,
-    pub center: Point,
-    pub width_real: f64,
-    pub height_real: f64,
+    center: Point,
+    width_real: f64,
+    height_real: f64,
+
+    precise_center: (Coordinate, Coordinate),
+    precise_width_real: Coordinate,
+    precise_height_real: Coordinate,
+}
+
+impl TryFrom<HistogramBuilderConf> for HistogramBuilder {
+    type Error = String;
+
+    fn try_from(conf: HistogramBuilderConf) -> Result<Self, Self::Error> {
+        for coordinate in [
+            &conf.center.0,
+            &conf.center.1,
+            &conf.width_real,
+            &conf.height_real,
+        ]
+        .iter()
+        {
+            if let Coordinate::Decimal(value) = coordinate {
+                if BigDecimal::from_str(value).is_err() {
+                    return Err(format!("invalid decimal coordinate {}", value));
+                }
+            }
+        }
+
+        Ok(HistogramBuilder {
+            width_px: conf.width_px,
+            height_px: conf.height_px,
+            resolution_px: conf.resolution_px,
+            center: (conf.center.0.to_f64(), conf.center.1.to_f64()),
+            width_real: conf.width_real.to_f64(),
+            height_real: conf.height_real.to_f64(),
+            precise_center: conf.center,
+            precise_width_real: conf.width_real,
+            precise_height_real: conf.height_real,
+        })
+    }
+}
+
+impl From<HistogramBuilder> for HistogramBuilderConf {
+    fn from(builder: HistogramBuilder) -> Self {
+        HistogramBuilderConf {
+            width_px: builder.width_px,
+            height_px: builder.height_px,
+            resolution_px: builder.resolution_px,
+            center: builder.precise_center,
+            width_real: builder.precise_width_real,
+            height_real: builder.precise_height_real,
+        }
+    }
 }
 
 impl HistogramBuilder {
+    pub fn new(
+        width_px: usize,
+        height_px: usize,
+        resolution_px: usize,
+        center: Point,
+        width_real: f64,
+        height_real: f64,
+    ) -> Self {
+        HistogramBuilder {
+            width_px,
+            height_px,
+            resolution_px,
+            center,
+            width_real,
+            height_real,
+            precise_center: (Coordinate::Float(center.0), Coordinate::Float(center.1)),
+            precise_width_real: Coordinate::Float(width_real),
+            precise_height_real: Coordinate::Float(height_real),
+        }
+    }
+
+    pub fn center(&self) -> Point {
+        self.center
+    }
+
+    pub fn width_real(&self) -> f64 {
+        self.width_real
+    }
+
+    pub fn height_real(&self) -> f64 {
+        self.height_real
+    }
+
+    /// Center of the viewport with the precision of the configuration
+    pub fn precise_center(&self) -> (BigDecimal, BigDecimal) {
+        (
+            self.precise_center.0.to_big_decimal(),
+            self.precise_center.1.to_big_decimal(),
+        )
+    }
+
+    /// Real width of the viewport with the precision of the configuration
+    pub fn precise_width_real(&self) -> BigDecimal {
+        self.precise_width_real.to_big_decimal()
+    }
+
+    /// Real height of the viewport with the precision of the configuration
+    pub fn precise_height_real(&self) -> BigDecimal {
+        self.precise_height_real.to_big_decimal()
+    }
+
     pub fn point_top_left(&self) -> Point {
         (
             self.center.0 - self.width_real / 2.,
@@ -25,7 +175,8 @@ impl HistogramBuilder {
         )
     }
 
-    fn pixel_width_real(&self) -> f64 {
+    /// Width of a virtual pixel in the plane
+    pub fn pixel_width_real(&self) -> f64 {
         self.width_real / (self.resolution_px * self.width_px) as f64
     }
 
@@ -61,21 +212,114 @@ impl HistogramBuilder {
         }
     }
 
+    fn pixel_position(&self, (x0, y0): Point, i: usize, j: usize) -> Point {
+        (
+            (i as f64) * self.pixel_width_real() + x0,
+            (j as f64) * self.pixel_width_real() + y0,
+        )
+    }
+
     pub fn iter_over_pixels(
         &self,
     ) -> Box<dyn std::iter::Iterator<Item = ((usize, usize), Point)> + '_> {
-        let (x0, y0) = self.point_top_left();
+        let top_left = self.point_top_left();
         box (0..self.width_px * self.resolution_px)
             .cartesian_product(0..self.height_px * self.resolution_px)
-            .map(move |(i, j)| {
-                (
-                    (i, j),
-                    (
-                        (i as f64) * self.pixel_width_real() + x0,
-                        (j as f64) * self.pixel_width_real() + y0,
-                    ),
-                )
+            .map(move |(i, j)| ((i, j), self.pixel_position(top_left, i, j)))
+    }
+
+    /// Compute `index_function` on the indices of every (virtual) pixel, one row per task on the
+    /// current rayon thread pool. The result is laid out like the histogram data:
+    /// `i + width * resolution * j`
+    fn par_map_indices<T, F>(&self, index_function: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(usize, usize) -> T + Sync,
+    {
+        self.par_map_indices_init(|| (), |_, i, j| index_function(i, j))
+    }
+
+    /// Same as `par_map_indices`, the pixels of each row sharing the state created by `init`
+    fn par_map_indices_init<S, T, I, F>(&self, init: I, index_function: F) -> Vec<T>
+    where
+        T: Send,
+        I: Fn() -> S + Sync,
+        F: Fn(&mut S, usize, usize) -> T + Sync,
+    {
+        let width = self.width_px * self.resolution_px;
+        (0..self.height_px * self.resolution_px)
+            .into_par_iter()
+            .flat_map_iter(|j| {
+                let (index_function, mut state) = (&index_function, init());
+                (0..width).map(move |i| index_function(&mut state, i, j))
             })
+            .collect()
+    }
+
+    /// Compute `pixel_function` on the position of every (virtual) pixel, in parallel
+    pub fn par_map_pixels<T, F>(&self, pixel_function: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(Point) -> T + Sync,
+    {
+        let top_left = self.point_top_left();
+        self.par_map_indices(|i, j| pixel_function(self.pixel_position(top_left, i, j)))
+    }
+
+    /// Same as `par_map_pixels`, the pixels of each row sharing the state created by `init`
+    /// (e.g. a buffer reused from one pixel to the next)
+    pub fn par_map_pixels_init<S, T, I, F>(&self, init: I, pixel_function: F) -> Vec<T>
+    where
+        T: Send,
+        I: Fn() -> S + Sync,
+        F: Fn(&mut S, Point) -> T + Sync,
+    {
+        let top_left = self.point_top_left();
+        self.par_map_indices_init(init, |state, i, j| {
+            pixel_function(state, self.pixel_position(top_left, i, j))
+        })
+    }
+
+    /// Compute `offset_function` on the offset of every (virtual) pixel from the center of the
+    /// viewport, in parallel. Unlike the positions, the offsets stay accurate for deep zooms.
+    pub fn par_map_pixel_offsets<T, F>(&self, offset_function: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(Point) -> T + Sync,
+    {
+        let top_left_offset = (-self.width_real / 2., -self.height_real / 2.);
+        self.par_map_indices(|i, j| offset_function(self.pixel_position(top_left_offset, i, j)))
+    }
+
+    /// Split `number_samples` among the threads of the current rayon thread pool, each worker
+    /// accumulating its share into a private histogram with `worker_function(worker, samples)`.
+    /// The partial histograms are merged in order, so that the result only depends on the number
+    /// of threads.
+    pub fn par_accumulate<F>(&self, number_samples: usize, worker_function: F) -> Histogram
+    where
+        F: Fn(usize, usize) -> Histogram + Sync,
+    {
+        let number_workers = rayon::current_num_threads();
+
+        let partial_histograms: Vec<Histogram> = (0..number_workers)
+            .into_par_iter()
+            .map(|worker| {
+                let samples = number_samples / number_workers
+                    + if worker < number_samples % number_workers {
+                        1
+                    } else {
+                        0
+                    };
+                worker_function(worker, samples)
+            })
+            .collect();
+
+        let mut partial_histograms = partial_histograms.into_iter();
+        let mut histogram = partial_histograms.next().unwrap();
+        for partial_histogram in partial_histograms {
+            histogram.merge(&partial_histogram);
+        }
+        histogram
     }
 }
 
@@ -85,12 +329,28 @@ pub enum FrequencyAggregationType {
     Logarithmic,
 }
 
-#[derive(Deserialize, Serialize)]
+/// Channels of the sums of the colors of the points of every cell, with components between 0 and 1.
+/// Unlike the other channels, they add up like the frequencies when the cells are merged.
+pub const RED_CHANNEL: &str = "red";
+pub const GREEN_CHANNEL: &str = "green";
+pub const BLUE_CHANNEL: &str = "blue";
+const COLOR_CHANNELS: [&str; 3] = [RED_CHANNEL, GREEN_CHANNEL, BLUE_CHANNEL];
+
+/// Named value stored for every cell of a histogram, alongside its frequency and color
+#[derive(Deserialize, Serialize, PartialEq, Clone)]
+pub struct HistogramChannel {
+    pub name: String,
+    data: Vec<f64>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
 pub struct Histogram {
     pub width: usize,
     pub height: usize,
     pub resolution: usize,
     data: Vec<HistogramCell>,
+    #[serde(default)]
+    channels: Vec<HistogramChannel>,
 }
 
 impl Histogram {
@@ -102,6 +362,7 @@ impl Histogram {
             height,
             resolution,
             data,
+            channels: vec![],
         }
     }
     pub fn get_cell(&self, x: usize, y: usize) -> HistogramCell {
@@ -110,7 +371,119 @@ impl Histogram {
     pub fn set_cell(&mut self, x: usize, y: usize, cell: HistogramCell) {
         self.data[x + self.width * self.resolution * y] = cell;
     }
+    /// Build a histogram from cells given in row-major order, as returned by
+    /// `HistogramBuilder::par_map_pixels`
+    pub fn from_cells(builder: &HistogramBuilder, data: Vec<HistogramCell>) -> Self {
+        assert_eq!(
+            data.len(),
+            builder.width_px * builder.height_px * builder.resolution_px * builder.resolution_px
+        );
+        Histogram {
+            width: builder.width_px,
+            height: builder.height_px,
+            resolution: builder.resolution_px,
+            data,
+            channels: vec![],
+        }
+    }
+    /// Add a named channel, its data being laid out like the cells
+    pub fn add_channel(&mut self, name: &str, data: Vec<f64>) {
+        assert_eq!(data.len(), self.data.len());
+        assert!(self.channel_index(name).is_none());
+        self.channels.push(HistogramChannel {
+            name: name.to_string(),
+            data,
+        });
+    }
+    /// Add the red, green and blue channels, with empty sums
+    pub fn add_color_channels(&mut self) -> [usize; 3] {
+        for name in COLOR_CHANNELS.iter() {
+            self.add_channel(name, vec![0.; self.data.len()]);
+        }
+        self.color_channels().unwrap()
+    }
+    /// Indices of the red, green and blue channels, if the histogram has all of them
+    pub fn color_channels(&self) -> Option<[usize; 3]> {
+        match (
+            self.channel_index(RED_CHANNEL),
+            self.channel_index(GREEN_CHANNEL),
+            self.channel_index(BLUE_CHANNEL),
+        ) {
+            (Some(red), Some(green), Some(blue)) => Some([red, green, blue]),
+            _ => None,
+        }
+    }
+    /// Add a color, with components between 0 and 1, to the color sums of the cell
+    pub fn add_color(&mut self, color_channels: [usize; 3], x: usize, y: usize, rgb: [f64; 3]) {
+        let index = x + self.width * self.resolution * y;
+        for (&channel, component) in color_channels.iter().zip(rgb.iter()) {
+            self.channels[channel].data[index] += component;
+        }
+    }
+    /// Sums of the colors of the points of the cell
+    pub fn get_color_sum(&self, color_channels: [usize; 3], x: usize, y: usize) -> [f64; 3] {
+        let index = x + self.width * self.resolution * y;
+        let mut rgb = [0.; 3];
+        for (component, &channel) in rgb.iter_mut().zip(color_channels.iter()) {
+            *component = self.channels[channel].data[index];
+        }
+        rgb
+    }
+    pub fn number_channels(&self) -> usize {
+        self.channels.len()
+    }
+    pub fn channel_name(&self, channel: usize) -> &str {
+        &self.channels[channel].name
+    }
+    pub fn channel_index(&self, name: &str) -> Option<usize> {
+        self.channels
+            .iter()
+            .position(|channel| channel.name == name)
+    }
+    pub fn get_channel_cell(&self, channel: usize, x: usize, y: usize) -> f64 {
+        self.channels[channel].data[x + self.width * self.resolution * y]
+    }
+    pub fn set_channel_cell(&mut self, channel: usize, x: usize, y: usize, value: f64) {
+        self.channels[channel].data[x + self.width * self.resolution * y] = value;
+    }
+    /// Accumulate the cells of `other` (of the same dimensions and channels) into this histogram,
+    /// the colors being averaged with respect to the frequencies
+    pub fn merge(&mut self, other: &Histogram) {
+        assert!(
+            self.width == other.width
+                && self.height == other.height
+                && self.resolution == other.resolution
+        );
+        assert_eq!(self.channels.len(), other.channels.len());
+        for (channel, other_channel) in self.channels.iter_mut().zip(other.channels.iter()) {
+            assert!(channel.name == other_channel.name);
+            for (value, other_value) in channel.data.iter_mut().zip(other_channel.data.iter()) {
+                *value += other_value;
+            }
+        }
+        for (cell, &(other_freq, other_color)) in self.data.iter_mut().zip(other.data.iter()) {
+            let (freq, color) = *cell;
+            let freq_sum = freq + other_freq;
+            if freq_sum > 0. {
+                *cell = (
+                    freq_sum,
+                    (color * freq + other_color * other_freq) / freq_sum,
+                );
+            }
+        }
+    }
+    /// Merge the virtual pixels of every real pixel, averaging their colors. Suited to the
+    /// escape-time histograms, whose frequency is an escape count rather than a number of points.
     pub fn reduce_resolution(&mut self, freq_agreg_type: FrequencyAggregationType) {
+        self.reduce_cells(freq_agreg_type, false);
+    }
+    /// Merge the virtual pixels of every real pixel, averaging their colors weighted by their
+    /// frequencies, so that the color is the mean one of the points accumulated in the pixel.
+    /// Suited to the point-density histograms, like the flame ones.
+    pub fn reduce_density_resolution(&mut self, freq_agreg_type: FrequencyAggregationType) {
+        self.reduce_cells(freq_agreg_type, true);
+    }
+    fn reduce_cells(&mut self, freq_agreg_type: FrequencyAggregationType, weighted_colors: bool) {
         let mut pixel_cumul: Vec<HistogramCell> = vec![];
 
         // Accumulation tab for the pixels
@@ -130,28 +503,51 @@ impl Histogram {
                     pixel_cumul[avg_point.0 + avg_point.1 * self.width];
 
                 freq_sum += freq;
-                color_sum += color;
+                color_sum += if weighted_colors { freq * color } else { color };
 
                 pixel_cumul[avg_point.0 + avg_point.1 * self.width] = (freq_sum, color_sum)
             }
         }
 
+        // The color channels are summed, and the other channels are averaged over each real pixel
+        let resolution_sq = (self.resolution * self.resolution) as f64;
+        for channel in self.channels.iter_mut() {
+            let weight = if COLOR_CHANNELS.contains(&channel.name.as_str()) {
+                1.
+            } else {
+                1. / resolution_sq
+            };
+            let mut channel_cumul = vec![0.; self.width * self.height];
+            for x in 0..(self.width * self.resolution) {
+                for y in 0..(self.height * self.resolution) {
+                    channel_cumul[x / self.resolution + (y / self.resolution) * self.width] +=
+                        channel.data[x + self.width * self.resolution * y] * weight;
+                }
+            }
+            channel.data = channel_cumul;
+        }
+
         self.resolution = 1;
 
-        // Now make the average for every pixels and compute the maximal frequency
+        // Now make the average of the colors for every pixel and compute the maximal frequency
         let mut max_freq: f64 = 0.;
         for x in 0..(self.width) {
             for y in 0..(self.height) {
                 let index = x + y * self.width;
-                let (freq_sum, mut color_sum) = pixel_cumul[index];
-                let resolution_sq = (self.resolution * self.resolution) as f64;
-                color_sum /= resolution_sq;
+                let (freq_sum, color_sum) = pixel_cumul[index];
+                let color = if !weighted_colors {
+                    color_sum / resolution_sq
+                } else if freq_sum > 0. {
+                    color_sum / freq_sum
+                } else {
+                    0.
+                };
 
                 if max_freq < freq_sum as f64 {
                     max_freq = freq_sum as f64;
                 }
 
-                pixel_cumul[index] = (freq_sum, color_sum);
+                pixel_cumul[index] = (freq_sum, color);
             }
         }
         // Aggregate the frequences
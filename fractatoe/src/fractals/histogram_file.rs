@@ -0,0 +1,284 @@
+//! Binary file format of the histograms.
+//!
+//! A file starts with a header: the magic bytes, the version of the format, the flags (precision
+//! and compression), the dimensions and the names of the channels, the integers being little
+//! endian. It is followed by the values of the cells (frequency and color), then by the values of
+//! each channel, possibly compressed with deflate. The legacy JSON histograms are still read.
+
+use std::io;
+use std::io::{Read, Write};
+use std::iter::once;
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde_derive::{Deserialize, Serialize};
+
+use super::histogram::Histogram;
+
+const MAGIC: &[u8; 8] = b"FRACTHST";
+const VERSION: u32 = 1;
+
+const FLAG_SINGLE_PRECISION: u32 = 1;
+const FLAG_COMPRESSED: u32 = 1 << 1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Precision {
+    Single,
+    Double,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct HistogramFileOptions {
+    /// Precision of the stored values, the single one halving the size of the file
+    pub precision: Precision,
+    pub compression: bool,
+}
+
+impl Default for HistogramFileOptions {
+    fn default() -> Self {
+        HistogramFileOptions {
+            precision: Precision::Double,
+            compression: true,
+        }
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "truncated histogram file".to_string(),
+    )
+}
+
+/// Values of the histogram, in the order of the file
+fn values(histogram: &Histogram) -> impl Iterator<Item = f64> + '_ {
+    let (width, height) = (
+        histogram.width * histogram.resolution,
+        histogram.height * histogram.resolution,
+    );
+    let cells = (0..height).flat_map(move |y| {
+        (0..width).flat_map(move |x| {
+            let (freq, color) = histogram.get_cell(x, y);
+            once(freq).chain(once(color))
+        })
+    });
+    let channels = (0..histogram.number_channels()).flat_map(move |channel| {
+        (0..height)
+            .flat_map(move |y| (0..width).map(move |x| histogram.get_channel_cell(channel, x, y)))
+    });
+    cells.chain(channels)
+}
+
+fn write_values<W: Write>(
+    histogram: &Histogram,
+    precision: Precision,
+    writer: &mut W,
+) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(writer);
+    for value in values(histogram) {
+        match precision {
+            Precision::Single => writer.write_all(&(value as f32).to_le_bytes())?,
+            Precision::Double => writer.write_all(&value.to_le_bytes())?,
+        }
+    }
+    writer.flush()
+}
+
+pub fn write_histogram<W: Write>(
+    histogram: &Histogram,
+    options: HistogramFileOptions,
+    mut writer: W,
+) -> io::Result<()> {
+    let mut flags = 0;
+    if options.precision == Precision::Single {
+        flags |= FLAG_SINGLE_PRECISION;
+    }
+    if options.compression {
+        flags |= FLAG_COMPRESSED;
+    }
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&flags.to_le_bytes())?;
+    for dimension in [histogram.width, histogram.height, histogram.resolution].iter() {
+        writer.write_all(&(*dimension as u64).to_le_bytes())?;
+    }
+    writer.write_all(&(histogram.number_channels() as u32).to_le_bytes())?;
+    for channel in 0..histogram.number_channels() {
+        let name = histogram.channel_name(channel).as_bytes();
+        writer.write_all(&(name.len() as u32).to_le_bytes())?;
+        writer.write_all(name)?;
+    }
+
+    if options.compression {
+        let mut encoder = DeflateEncoder::new(writer, Compression::default());
+        write_values(histogram, options.precision, &mut encoder)?;
+        encoder.finish()?;
+        Ok(())
+    } else {
+        write_values(histogram, options.precision, &mut writer)
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Read `length` bytes. The buffer grows with the bytes actually read, rather than being
+/// allocated from a length which may come from a corrupt file.
+fn read_bytes<R: Read>(reader: &mut R, length: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    reader.take(length as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != length {
+        return Err(truncated());
+    }
+    Ok(bytes)
+}
+
+/// Read `number` values stored with the given precision
+fn read_values<R: Read>(
+    reader: &mut R,
+    precision: Precision,
+    number: usize,
+) -> io::Result<Vec<f64>> {
+    let size = match precision {
+        Precision::Single => 4,
+        Precision::Double => 8,
+    };
+    let length = number
+        .checked_mul(size)
+        .ok_or_else(|| invalid_data(format!("too many values ({}) in the histogram", number)))?;
+    let bytes = read_bytes(reader, length)?;
+    Ok(bytes
+        .chunks_exact(size)
+        .map(|value| match precision {
+            Precision::Single => {
+                f32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64
+            }
+            Precision::Double => f64::from_le_bytes([
+                value[0], value[1], value[2], value[3], value[4], value[5], value[6], value[7],
+            ]),
+        })
+        .collect())
+}
+
+fn read_binary_histogram<R: Read>(mut reader: R) -> io::Result<Histogram> {
+    let version = read_u32(&mut reader)?;
+    if version != VERSION {
+        return Err(invalid_data(format!(
+            "unsupported version {} of the histogram file",
+            version
+        )));
+    }
+    let flags = read_u32(&mut reader)?;
+    let precision = if flags & FLAG_SINGLE_PRECISION != 0 {
+        Precision::Single
+    } else {
+        Precision::Double
+    };
+    let (width, height, resolution) = (
+        read_dimension(&mut reader)?,
+        read_dimension(&mut reader)?,
+        read_dimension(&mut reader)?,
+    );
+    let number_channels = read_u32(&mut reader)?;
+    let mut names: Vec<String> = vec![];
+    for _ in 0..number_channels {
+        let length = read_u32(&mut reader)? as usize;
+        let name = String::from_utf8(read_bytes(&mut reader, length)?)
+            .map_err(|error| invalid_data(error.to_string()))?;
+        if names.contains(&name) {
+            return Err(invalid_data(format!("duplicate channel {}", name)));
+        }
+        names.push(name);
+    }
+
+    if flags & FLAG_COMPRESSED != 0 {
+        read_data(
+            DeflateDecoder::new(reader),
+            precision,
+            (width, height, resolution),
+            &names,
+        )
+    } else {
+        read_data(reader, precision, (width, height, resolution), &names)
+    }
+}
+
+fn read_dimension<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let dimension = read_u64(reader)?;
+    if dimension > usize::MAX as u64 {
+        return Err(invalid_data(format!(
+            "dimension {} of the histogram too large",
+            dimension
+        )));
+    }
+    Ok(dimension as usize)
+}
+
+/// Read the cells and the channels of the histogram following the header
+fn read_data<R: Read>(
+    mut reader: R,
+    precision: Precision,
+    (width, height, resolution): (usize, usize, usize),
+    names: &[String],
+) -> io::Result<Histogram> {
+    if resolution == 0 {
+        return Err(invalid_data("null resolution of the histogram".to_string()));
+    }
+    let number_cells = width
+        .checked_mul(resolution)
+        .and_then(|row| Some((row, height.checked_mul(resolution)?)))
+        .and_then(|(row, column)| row.checked_mul(column))
+        .and_then(|number_cells| Some((number_cells, number_cells.checked_mul(2)?)));
+    let (number_cells, number_values) = number_cells.ok_or_else(|| {
+        invalid_data(format!(
+            "dimensions {}x{}x{} of the histogram too large",
+            width, height, resolution
+        ))
+    })?;
+
+    // The values are read before the histogram is allocated, so that a corrupt header fails
+    // on the missing data
+    let cells = read_values(&mut reader, precision, number_values)?;
+    let mut histogram = Histogram::new(width, height, resolution);
+    for (index, cell) in cells.chunks_exact(2).enumerate() {
+        let (x, y) = (index % (width * resolution), index / (width * resolution));
+        histogram.set_cell(x, y, (cell[0], cell[1]));
+    }
+    for name in names.iter() {
+        histogram.add_channel(name, read_values(&mut reader, precision, number_cells)?);
+    }
+    Ok(histogram)
+}
+
+/// Read a histogram in the binary format, or in the legacy JSON one
+pub fn read_histogram<R: Read>(mut reader: R) -> io::Result<Histogram> {
+    let mut magic = [0; 8];
+    let mut read = 0;
+    while read < magic.len() {
+        match reader.read(&mut magic[read..])? {
+            0 => break,
+            bytes => read += bytes,
+        }
+    }
+    if &magic == MAGIC {
+        read_binary_histogram(reader)
+    } else {
+        serde_json::from_reader((&magic[..read]).chain(reader))
+            .map_err(|error| invalid_data(error.to_string()))
+    }
+}
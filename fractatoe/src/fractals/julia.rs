@@ -1,5 +1,10 @@
 use super::{
+    escape_time::{
+        escape_time_histogram_with_colors, exterior_distance_estimate, smooth_iteration_count,
+        Escape, DISTANCE_CHANNEL,
+    },
     histogram::{Histogram, HistogramBuilder},
+    orbit_trap::{OrbitTrap, OrbitTrapConf, TrapTracker, TRAP_ITERATION_CHANNEL},
     HistogramGeneration,
 };
 use num::complex::Complex;
@@ -10,30 +15,108 @@ pub struct Julia {
     c: (f64, f64),
     bound: f64,
     iterations: usize,
+    /// Store the fractional iteration count instead of the escape index
+    #[serde(default)]
+    smooth: bool,
+    /// Store the exterior distance estimate of the escaping points in the distance channel
+    #[serde(default)]
+    distance_estimation: bool,
+    /// Color the points by the minimum distance of their orbit to this trap
+    orbit_trap: Option<OrbitTrapConf>,
 }
 
-fn julia_divergence(x: f64, y: f64, c: Complex<f64>, bound: f64, iterations: usize) -> f64 {
-    let mut z = Complex::new(x, y);
+impl Julia {
+    /// Names of the additional channels, in the order of the values given by `divergence`
+    fn channel_names(&self) -> Vec<&'static str> {
+        let mut names = vec![];
+        if self.distance_estimation {
+            names.push(DISTANCE_CHANNEL);
+        }
+        if self.orbit_trap.is_some() {
+            names.push(TRAP_ITERATION_CHANNEL);
+        }
+        names
+    }
 
-    for i in 0..iterations {
-        if z.norm() > bound {
-            return i as f64;
+    /// Escape of the point z, with its color (the minimum distance to the trap) and the values of
+    /// the additional channels
+    fn divergence(
+        &self,
+        mut z: Complex<f64>,
+        trap: Option<&OrbitTrap>,
+        pixel_width: f64,
+    ) -> (Escape, f64, Vec<f64>) {
+        let c = Complex::new(self.c.0, self.c.1);
+        // Derivative of z with respect to its starting point
+        let mut derivative = Complex::new(1., 0.);
+        let mut tracker = trap.map(TrapTracker::new);
+
+        let values = |escape: Escape, distance: f64, tracker: &Option<TrapTracker>| {
+            let mut values = vec![];
+            if self.distance_estimation {
+                values.push(distance / pixel_width);
+            }
+            let (color, trap_iteration) = tracker.as_ref().map_or((0., 0.), TrapTracker::values);
+            if tracker.is_some() {
+                values.push(trap_iteration);
+            }
+            (escape, color, values)
+        };
+
+        // The starting point, the pixel itself, is part of the orbit
+        if let Some(tracker) = &mut tracker {
+            tracker.update(0, z);
+        }
+        for i in 0..self.iterations {
+            if z.norm() > self.bound {
+                let escape = if self.smooth {
+                    smooth_iteration_count(i, z, 2.)
+                } else {
+                    i as f64
+                };
+                let distance = if self.distance_estimation {
+                    exterior_distance_estimate(z, derivative)
+                } else {
+                    0.
+                };
+                return values(Escape::Escaped(escape), distance, &tracker);
+            }
+            if self.distance_estimation {
+                derivative = 2. * z * derivative;
+            }
+            z = z * z + c;
+            if let Some(tracker) = &mut tracker {
+                tracker.update(i + 1, z);
+            }
         }
-        z = z * z + c;
+        values(Escape::Interior, 0., &tracker)
     }
-    0.
 }
 
-impl HistogramGeneration for Julia {
+impl Julia {
+    /// Load the orbit trap, whose image may be missing or invalid
+    pub fn build(self) -> Result<JuliaAlgorithm, png::DecodingError> {
+        let trap = self
+            .orbit_trap
+            .as_ref()
+            .map(OrbitTrapConf::build)
+            .transpose()?;
+        Ok(JuliaAlgorithm { julia: self, trap })
+    }
+}
+
+pub struct JuliaAlgorithm {
+    julia: Julia,
+    trap: Option<OrbitTrap>,
+}
+
+impl HistogramGeneration for JuliaAlgorithm {
     fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
-        let mut histogram =
-            Histogram::new(builder.width_px, builder.height_px, builder.resolution_px);
-        let c = Complex::new(self.c.0, self.c.1);
-        for ((i, j), (x_float, y_float)) in builder.iter_over_pixels() {
-            let div = julia_divergence(x_float, y_float, c, self.bound, self.iterations);
-            histogram.set_cell(i, j, (div as f64, 0.))
-        }
+        let (julia, trap) = (&self.julia, self.trap.as_ref());
+        let pixel_width = builder.pixel_width_real();
 
-        histogram
+        escape_time_histogram_with_colors(&builder, &julia.channel_names(), |(x_float, y_float)| {
+            julia.divergence(Complex::new(x_float, y_float), trap, pixel_width)
+        })
     }
 }
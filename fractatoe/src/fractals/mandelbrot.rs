@@ -1,38 +1,160 @@
 use num::complex::Complex;
 use serde_derive::{Deserialize, Serialize};
 
+use super::escape_time::{
+    escape_time_histogram_with_colors, exterior_distance_estimate, in_main_cardioid_or_bulb,
+    smooth_iteration_count, Escape, DISTANCE_CHANNEL,
+};
 use super::histogram::{Histogram, HistogramBuilder};
+use super::orbit_trap::{OrbitTrap, OrbitTrapConf, TrapTracker, TRAP_ITERATION_CHANNEL};
+use super::perturbation::DeepZoom;
 use super::HistogramGeneration;
 
 #[derive(Serialize, Deserialize)]
 pub struct Mandelbrot {
     bound: f64,
     iterations: usize,
+    /// Store the fractional iteration count instead of the escape index
+    #[serde(default)]
+    smooth: bool,
+    /// Store the exterior distance estimate of the escaping points in the distance channel
+    #[serde(default)]
+    distance_estimation: bool,
+    /// Color the points by the minimum distance of their orbit to this trap
+    orbit_trap: Option<OrbitTrapConf>,
+    /// Render by perturbation of a high-precision reference orbit, for zooms beyond the
+    /// precision of f64
+    deep_zoom: Option<DeepZoom>,
 }
 
-fn mandelbrot_divergence(x: f64, y: f64, bound: f64, iterations: usize) -> usize {
-    let mut z = Complex::new(0., 0.);
+impl Mandelbrot {
+    /// Names of the additional channels, in the order of the values given by `divergence`
+    fn channel_names(&self) -> Vec<&'static str> {
+        let mut names = vec![];
+        if self.distance_estimation {
+            names.push(DISTANCE_CHANNEL);
+        }
+        if self.orbit_trap.is_some() {
+            names.push(TRAP_ITERATION_CHANNEL);
+        }
+        names
+    }
+
+    /// Escape of c, with its color (the minimum distance to the trap) and the values of the
+    /// additional channels
+    fn divergence(
+        &self,
+        c: Complex<f64>,
+        trap: Option<&OrbitTrap>,
+        pixel_width: f64,
+    ) -> (Escape, f64, Vec<f64>) {
+        let mut z = Complex::new(0., 0.);
+        // Derivative of z with respect to c
+        let mut derivative = Complex::new(0., 0.);
+        let mut tracker = trap.map(TrapTracker::new);
+
+        let values = |escape: Escape, distance: f64, tracker: &Option<TrapTracker>| {
+            let mut values = vec![];
+            if self.distance_estimation {
+                values.push(distance / pixel_width);
+            }
+            let (color, trap_iteration) = tracker.as_ref().map_or((0., 0.), TrapTracker::values);
+            if tracker.is_some() {
+                values.push(trap_iteration);
+            }
+            (escape, color, values)
+        };
+
+        // The orbits of the points of the Mandelbrot set stay in the disk of radius 2. The orbit
+        // is needed to color the points with a trap.
+        if self.bound >= 2. && trap.is_none() && in_main_cardioid_or_bulb(c) {
+            return values(Escape::Interior, 0., &tracker);
+        }
+
+        // Brent's cycle detection: z is compared to a value saved at increasing powers of 2. An
+        // orbit which comes back exactly to a previous value is periodic and never escapes (nor
+        // comes closer to the trap).
+        let mut saved = z;
+        let mut period = 0;
+        let mut power = 1;
 
-    let c = Complex::new(x, y);
+        for i in 0..self.iterations {
+            if z.norm() > self.bound {
+                let escape = if self.smooth {
+                    smooth_iteration_count(i, z, 2.)
+                } else {
+                    i as f64
+                };
+                let distance = if self.distance_estimation {
+                    exterior_distance_estimate(z, derivative)
+                } else {
+                    0.
+                };
+                return values(Escape::Escaped(escape), distance, &tracker);
+            }
+            if self.distance_estimation {
+                derivative = 2. * z * derivative + 1.;
+            }
+            z = z * z + c;
+            if let Some(tracker) = &mut tracker {
+                tracker.update(i + 1, z);
+            }
 
-    for i in 0..iterations {
-        if z.norm() > bound {
-            return i;
+            if z == saved {
+                return values(Escape::Interior, 0., &tracker);
+            }
+            period += 1;
+            if period == power {
+                saved = z;
+                period = 0;
+                power *= 2;
+            }
         }
-        z = z * z + c;
+        values(Escape::Interior, 0., &tracker)
+    }
+}
+
+impl Mandelbrot {
+    /// Load the orbit trap, whose image may be missing or invalid
+    pub fn build(self) -> Result<MandelbrotAlgorithm, png::DecodingError> {
+        let trap = self
+            .orbit_trap
+            .as_ref()
+            .map(OrbitTrapConf::build)
+            .transpose()?;
+        Ok(MandelbrotAlgorithm {
+            mandelbrot: self,
+            trap,
+        })
     }
-    0
 }
-impl HistogramGeneration for Mandelbrot {
-    fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
-        let mut histogram =
-            Histogram::new(builder.width_px, builder.height_px, builder.resolution_px);
 
-        for ((i, j), (x_float, y_float)) in builder.iter_over_pixels() {
-            let div = mandelbrot_divergence(x_float, y_float, self.bound, self.iterations);
-            histogram.set_cell(i, j, (div as f64, 0.))
+pub struct MandelbrotAlgorithm {
+    mandelbrot: Mandelbrot,
+    trap: Option<OrbitTrap>,
+}
+
+impl HistogramGeneration for MandelbrotAlgorithm {
+    fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
+        let (mandelbrot, trap) = (&self.mandelbrot, self.trap.as_ref());
+        if let Some(deep_zoom) = &mandelbrot.deep_zoom {
+            return deep_zoom.build_histogram(
+                mandelbrot.bound,
+                mandelbrot.iterations,
+                mandelbrot.smooth,
+                mandelbrot.distance_estimation,
+                trap,
+                &builder,
+            );
         }
+        let pixel_width = builder.pixel_width_real();
 
-        histogram
+        escape_time_histogram_with_colors(
+            &builder,
+            &mandelbrot.channel_names(),
+            |(x_float, y_float)| {
+                mandelbrot.divergence(Complex::new(x_float, y_float), trap, pixel_width)
+            },
+        )
     }
 }
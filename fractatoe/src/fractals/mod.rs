@@ -1,7 +1,20 @@
+pub mod buddhabrot;
+pub mod burning_ship;
+pub mod escape_time;
 pub mod flame;
+pub mod formula;
 pub mod histogram;
+pub mod histogram_file;
 pub mod julia;
 pub mod mandelbrot;
+pub mod multibrot;
+pub mod newton;
+pub mod orbit_trap;
+pub mod perturbation;
+pub mod tricorn;
+
+#[cfg(test)]
+mod test;
 
 pub trait HistogramGeneration {
     fn build_histogram(self, builder: histogram::HistogramBuilder) -> histogram::Histogram;
@@ -0,0 +1,85 @@
+use log::warn;
+use num::complex::Complex;
+use serde_derive::{Deserialize, Serialize};
+
+use super::escape_time::{escape_time_histogram, iterate_until_escape};
+use super::histogram::{Histogram, HistogramBuilder};
+use super::HistogramGeneration;
+
+/// z -> z^exponent + c, iterated from 0 for every c of the plane. The exponent may be non-integer
+/// (principal branch of the power) or negative.
+#[derive(Serialize, Deserialize)]
+pub struct Multibrot {
+    exponent: f64,
+    bound: f64,
+    iterations: usize,
+    #[serde(default)]
+    smooth: bool,
+}
+
+/// z -> z^exponent + c for a fixed c, iterated from every z of the plane
+#[derive(Serialize, Deserialize)]
+pub struct MultibrotJulia {
+    exponent: f64,
+    c: (f64, f64),
+    bound: f64,
+    iterations: usize,
+    #[serde(default)]
+    smooth: bool,
+}
+
+fn multibrot_step(z: Complex<f64>, c: Complex<f64>, exponent: f64) -> Complex<f64> {
+    if exponent.fract() == 0. {
+        z.powi(exponent as i32) + c
+    } else {
+        z.powf(exponent) + c
+    }
+}
+
+/// The log-log renormalization only makes sense for the growth rate of the iteration, |exponent|,
+/// and is undefined when it is not above 1: the escape index is kept in that case
+fn smooth_degree(smooth: bool, exponent: f64) -> Option<f64> {
+    if !smooth {
+        None
+    } else if exponent.abs() > 1. {
+        Some(exponent.abs())
+    } else {
+        warn!("No smooth iteration count for the exponent {}", exponent);
+        None
+    }
+}
+
+impl HistogramGeneration for Multibrot {
+    fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
+        let smooth_degree = smooth_degree(self.smooth, self.exponent);
+        escape_time_histogram(&builder, |(x_float, y_float)| {
+            let c = Complex::new(x_float, y_float);
+            // Start from the first iterate, 0^exponent being undefined for negative exponents
+            iterate_until_escape(
+                c,
+                1,
+                |z| multibrot_step(z, c, self.exponent),
+                self.bound,
+                self.iterations,
+                smooth_degree,
+            )
+        })
+    }
+}
+
+impl HistogramGeneration for MultibrotJulia {
+    fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
+        let smooth_degree = smooth_degree(self.smooth, self.exponent);
+        let c = Complex::new(self.c.0, self.c.1);
+        escape_time_histogram(&builder, |(x_float, y_float)| {
+            iterate_until_escape(
+                Complex::new(x_float, y_float),
+                0,
+                |z| multibrot_step(z, c, self.exponent),
+                self.bound,
+                self.iterations,
+                smooth_degree,
+            )
+        })
+    }
+}
@@ -0,0 +1,152 @@
+use std::convert::TryFrom;
+
+use num::complex::Complex;
+use serde_derive::{Deserialize, Serialize};
+
+use super::escape_time::{escape_time_histogram_with_channels, Escape};
+use super::histogram::{Histogram, HistogramBuilder};
+use super::HistogramGeneration;
+
+/// Channel holding 1 + the index of the root to which the point converged, 0 if it converged to
+/// none of them
+pub const ROOT_CHANNEL: &str = "root";
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub enum NewtonVariant {
+    /// z -> z - a p(z)/p'(z), starting from every point of the plane
+    Newton,
+    /// z -> z - a p(z)/p'(z) + c, starting from `initial_z` for every c of the plane
+    Nova { initial_z: (f64, f64) },
+}
+
+fn default_variant() -> NewtonVariant {
+    NewtonVariant::Newton
+}
+
+fn default_relaxation() -> (f64, f64) {
+    (1., 0.)
+}
+
+/// Coefficients of a polynomial of degree at least 1, from the constant term to the leading one
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(try_from = "Vec<(f64, f64)>", into = "Vec<(f64, f64)>")]
+struct Polynomial(Vec<Complex<f64>>);
+
+impl TryFrom<Vec<(f64, f64)>> for Polynomial {
+    type Error = String;
+
+    fn try_from(coefficients: Vec<(f64, f64)>) -> Result<Self, Self::Error> {
+        if coefficients.len() < 2 {
+            return Err("the Newton polynomial must have a degree of at least 1".to_string());
+        }
+        if coefficients[coefficients.len() - 1] == (0., 0.) {
+            return Err("the leading coefficient of the Newton polynomial is 0".to_string());
+        }
+        Ok(Polynomial(
+            coefficients
+                .into_iter()
+                .map(|(re, im)| Complex::new(re, im))
+                .collect(),
+        ))
+    }
+}
+
+impl From<Polynomial> for Vec<(f64, f64)> {
+    fn from(polynomial: Polynomial) -> Self {
+        polynomial.0.into_iter().map(|z| (z.re, z.im)).collect()
+    }
+}
+
+/// Newton's method on a polynomial. The number of iterations needed to converge is stored as the
+/// frequency, and the root reached in the root channel. Points which did not converge are flagged
+/// as interior points.
+#[derive(Serialize, Deserialize)]
+pub struct Newton {
+    coefficients: Polynomial,
+    /// Relaxation factor a, 1 for the plain Newton method
+    #[serde(default = "default_relaxation")]
+    relaxation: (f64, f64),
+    #[serde(default = "default_variant")]
+    variant: NewtonVariant,
+    /// The point has converged when two consecutive iterates are closer than this
+    tolerance: f64,
+    iterations: usize,
+}
+
+/// Value of the polynomial and of its derivative at z (Horner's method)
+fn evaluate_polynomial(
+    coefficients: &[Complex<f64>],
+    z: Complex<f64>,
+) -> (Complex<f64>, Complex<f64>) {
+    let mut value = Complex::new(0., 0.);
+    let mut derivative = Complex::new(0., 0.);
+    for coefficient in coefficients.iter().rev() {
+        derivative = derivative * z + value;
+        value = value * z + coefficient;
+    }
+    (value, derivative)
+}
+
+/// All the complex roots of the polynomial (Durand-Kerner method)
+fn polynomial_roots(coefficients: &[Complex<f64>]) -> Vec<Complex<f64>> {
+    let leading = coefficients[coefficients.len() - 1];
+    let monic: Vec<Complex<f64>> = coefficients.iter().map(|c| c / leading).collect();
+    let degree = monic.len() - 1;
+
+    let seed = Complex::new(0.4, 0.9);
+    let mut roots: Vec<Complex<f64>> = (0..degree).map(|k| seed.powi(k as i32)).collect();
+    for _ in 0..1000 {
+        let mut max_step: f64 = 0.;
+        for i in 0..degree {
+            let (value, _) = evaluate_polynomial(&monic, roots[i]);
+            let mut denominator = Complex::new(1., 0.);
+            for j in 0..degree {
+                if i != j {
+                    denominator *= roots[i] - roots[j];
+                }
+            }
+            let step = value / denominator;
+            roots[i] -= step;
+            max_step = max_step.max(step.norm());
+        }
+        if max_step < 1e-15 {
+            break;
+        }
+    }
+    roots
+}
+
+impl HistogramGeneration for Newton {
+    fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
+        let coefficients = &self.coefficients.0;
+        let roots = polynomial_roots(coefficients);
+        let relaxation = Complex::new(self.relaxation.0, self.relaxation.1);
+
+        // Distance under which a limit point is considered to be a root
+        let root_distance = self.tolerance.sqrt().max(1e-6);
+
+        escape_time_histogram_with_channels(&builder, &[ROOT_CHANNEL], |(x_float, y_float)| {
+            let point = Complex::new(x_float, y_float);
+            let (mut z, c) = match self.variant {
+                NewtonVariant::Newton => (point, Complex::new(0., 0.)),
+                NewtonVariant::Nova { initial_z } => {
+                    (Complex::new(initial_z.0, initial_z.1), point)
+                }
+            };
+
+            for i in 0..self.iterations {
+                let (value, derivative) = evaluate_polynomial(coefficients, z);
+                let z_next = z - relaxation * value / derivative + c;
+                if (z_next - z).norm() < self.tolerance {
+                    let root = roots
+                        .iter()
+                        .position(|root| (root - z_next).norm() < root_distance)
+                        .map_or(0., |index| (index + 1) as f64);
+                    return (Escape::Escaped(i as f64), vec![root]);
+                }
+                z = z_next;
+            }
+            (Escape::Interior, vec![0.])
+        })
+    }
+}
@@ -0,0 +1,199 @@
+use std::fs::File;
+
+use num::complex::Complex;
+use serde_derive::{Deserialize, Serialize};
+
+/// Channel holding 1 + the iteration at which the orbit came closest to the trap, 0 if the orbit
+/// never hit the trap
+pub const TRAP_ITERATION_CHANNEL: &str = "trap_iteration";
+
+/// Shape to which the distance of the orbit is measured. The minimum distance over the orbit is
+/// stored as the color of the point.
+#[derive(Serialize, Deserialize, Clone)]
+pub enum OrbitTrapConf {
+    Point {
+        point: (f64, f64),
+    },
+    /// Infinite line through `point`
+    Line {
+        point: (f64, f64),
+        direction: (f64, f64),
+    },
+    /// Horizontal and vertical lines through `point`
+    Cross {
+        point: (f64, f64),
+    },
+    Circle {
+        center: (f64, f64),
+        radius: f64,
+    },
+    /// PNG image covering the given rectangle of the plane. The distance is 1 - the brightness
+    /// of the pixel hit (from 0 to 1), the transparent pixels and the outside of the image being
+    /// missed.
+    Image {
+        filename: String,
+        center: (f64, f64),
+        width: f64,
+        height: f64,
+    },
+}
+
+impl OrbitTrapConf {
+    pub fn build(&self) -> Result<OrbitTrap, png::DecodingError> {
+        let complex = |(re, im): (f64, f64)| Complex::new(re, im);
+        Ok(match self {
+            OrbitTrapConf::Point { point } => OrbitTrap::Point(complex(*point)),
+            OrbitTrapConf::Line { point, direction } => {
+                let direction = complex(*direction);
+                OrbitTrap::Line {
+                    point: complex(*point),
+                    direction: direction / direction.norm(),
+                }
+            }
+            OrbitTrapConf::Cross { point } => OrbitTrap::Cross(complex(*point)),
+            OrbitTrapConf::Circle { center, radius } => OrbitTrap::Circle {
+                center: complex(*center),
+                radius: *radius,
+            },
+            OrbitTrapConf::Image {
+                filename,
+                center,
+                width,
+                height,
+            } => OrbitTrap::Image {
+                image: TrapImage::load(filename)?,
+                top_left: Complex::new(center.0 - width / 2., center.1 - height / 2.),
+                size: Complex::new(*width, *height),
+            },
+        })
+    }
+}
+
+/// Distances of the pixels of a trap image, None for the transparent ones
+pub struct TrapImage {
+    width: usize,
+    height: usize,
+    distances: Vec<Option<f64>>,
+}
+
+impl TrapImage {
+    fn load(filename: &str) -> Result<Self, png::DecodingError> {
+        let mut decoder = png::Decoder::new(File::open(filename)?);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let (info, mut reader) = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buffer)?;
+
+        let (color_type, _) = reader.output_color_type();
+        let samples = color_type.samples();
+        let has_alpha = matches!(
+            color_type,
+            png::ColorType::RGBA | png::ColorType::GrayscaleAlpha
+        );
+        let color_samples = if has_alpha { samples - 1 } else { samples };
+
+        let distances = buffer
+            .chunks_exact(samples)
+            .map(|pixel| {
+                if has_alpha && pixel[samples - 1] == 0 {
+                    return None;
+                }
+                let brightness = pixel[..color_samples]
+                    .iter()
+                    .map(|&value| value as f64)
+                    .sum::<f64>()
+                    / (255. * color_samples as f64);
+                Some(1. - brightness)
+            })
+            .collect();
+
+        Ok(TrapImage {
+            width: info.width as usize,
+            height: info.height as usize,
+            distances,
+        })
+    }
+}
+
+pub enum OrbitTrap {
+    Point(Complex<f64>),
+    Line {
+        point: Complex<f64>,
+        /// Unit vector
+        direction: Complex<f64>,
+    },
+    Cross(Complex<f64>),
+    Circle {
+        center: Complex<f64>,
+        radius: f64,
+    },
+    Image {
+        image: TrapImage,
+        top_left: Complex<f64>,
+        size: Complex<f64>,
+    },
+}
+
+impl OrbitTrap {
+    /// Distance from z to the trap, None if z misses it
+    pub fn distance(&self, z: Complex<f64>) -> Option<f64> {
+        match self {
+            OrbitTrap::Point(point) => Some((z - point).norm()),
+            OrbitTrap::Line { point, direction } => {
+                let offset = z - point;
+                // Norm of the component of the offset orthogonal to the line
+                Some((offset.im * direction.re - offset.re * direction.im).abs())
+            }
+            OrbitTrap::Cross(point) => Some((z.re - point.re).abs().min((z.im - point.im).abs())),
+            OrbitTrap::Circle { center, radius } => Some(((z - center).norm() - radius).abs()),
+            OrbitTrap::Image {
+                image,
+                top_left,
+                size,
+            } => {
+                let x = (z.re - top_left.re) / size.re * image.width as f64;
+                let y = (z.im - top_left.im) / size.im * image.height as f64;
+                if x < 0. || y < 0. || x >= image.width as f64 || y >= image.height as f64 {
+                    return None;
+                }
+                image.distances[x as usize + y as usize * image.width]
+            }
+        }
+    }
+}
+
+/// Minimum distance to a trap along an orbit
+pub struct TrapTracker<'a> {
+    trap: &'a OrbitTrap,
+    min_distance: f64,
+    iteration: Option<usize>,
+}
+
+impl<'a> TrapTracker<'a> {
+    pub fn new(trap: &'a OrbitTrap) -> Self {
+        TrapTracker {
+            trap,
+            min_distance: f64::INFINITY,
+            iteration: None,
+        }
+    }
+
+    /// Take into account z, the value of the orbit at `iteration`
+    pub fn update(&mut self, iteration: usize, z: Complex<f64>) {
+        if let Some(distance) = self.trap.distance(z) {
+            if distance < self.min_distance {
+                self.min_distance = distance;
+                self.iteration = Some(iteration);
+            }
+        }
+    }
+
+    /// Minimum distance (the color of the point) and value of the trap iteration channel, both 0
+    /// if the orbit never hit the trap
+    pub fn values(&self) -> (f64, f64) {
+        match self.iteration {
+            Some(iteration) => (self.min_distance, (iteration + 1) as f64),
+            None => (0., 0.),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bigdecimal::{BigDecimal, ToPrimitive};
+use log::info;
+use num::complex::Complex;
+use serde_derive::{Deserialize, Serialize};
+
+use super::escape_time::{
+    escape_time_histogram_from_escapes, exterior_distance_estimate, smooth_iteration_count, Escape,
+    DISTANCE_CHANNEL,
+};
+use super::histogram::{Histogram, HistogramBuilder};
+use super::orbit_trap::{OrbitTrap, TrapTracker, TRAP_ITERATION_CHANNEL};
+
+/// Deep zoom of the Mandelbrot set by perturbation: the orbit of the center of the viewport (the
+/// reference) is computed once with arbitrary precision, and every pixel is iterated as an f64
+/// delta from this orbit. The center of the viewport should be given as decimal strings.
+#[derive(Serialize, Deserialize)]
+pub struct DeepZoom {
+    /// Number of significant digits of the reference orbit, deduced from the zoom if absent
+    precision: Option<u64>,
+}
+
+/// Complex number with arbitrary precision parts
+struct BigComplex {
+    re: BigDecimal,
+    im: BigDecimal,
+}
+
+impl BigComplex {
+    fn to_complex(&self) -> Complex<f64> {
+        Complex::new(
+            self.re.to_f64().unwrap_or(f64::NAN),
+            self.im.to_f64().unwrap_or(f64::NAN),
+        )
+    }
+
+    /// z^2 + c, rounded to `precision` significant digits
+    fn square_add(&self, c: &BigComplex, precision: u64) -> BigComplex {
+        let re = &self.re * &self.re - &self.im * &self.im + &c.re;
+        let im = (&self.re * &self.im).double() + &c.im;
+        BigComplex {
+            re: re.with_prec(precision),
+            im: im.with_prec(precision),
+        }
+    }
+}
+
+impl DeepZoom {
+    fn precision(&self, builder: &HistogramBuilder) -> u64 {
+        self.precision.unwrap_or_else(|| {
+            let pixel_size = builder.pixel_width_real();
+            (-pixel_size.log10()).max(0.) as u64 + 20
+        })
+    }
+
+    /// Orbit of the center up to `iterations` or until it escapes, rounded to f64
+    fn reference_orbit(
+        &self,
+        bound: f64,
+        iterations: usize,
+        precision: u64,
+        builder: &HistogramBuilder,
+    ) -> Vec<Complex<f64>> {
+        let (re, im) = builder.precise_center();
+        let c = BigComplex { re, im };
+
+        let mut z = BigComplex {
+            re: BigDecimal::from(0),
+            im: BigDecimal::from(0),
+        };
+        let mut orbit = vec![z.to_complex()];
+        for _ in 0..iterations {
+            z = z.square_add(&c, precision);
+            let z_float = z.to_complex();
+            orbit.push(z_float);
+            if z_float.norm() > bound {
+                break;
+            }
+        }
+        orbit
+    }
+
+    pub fn build_histogram(
+        &self,
+        bound: f64,
+        iterations: usize,
+        smooth: bool,
+        distance_estimation: bool,
+        trap: Option<&OrbitTrap>,
+        builder: &HistogramBuilder,
+    ) -> Histogram {
+        let reference = self.reference_orbit(bound, iterations, self.precision(builder), builder);
+        info!("Reference orbit of {} iterations", reference.len() - 1);
+
+        let pixel_width = builder.pixel_width_real();
+        let rebases = AtomicUsize::new(0);
+        let escapes = builder.par_map_pixel_offsets(|(dx, dy)| {
+            let delta_c = Complex::new(dx, dy);
+            let mut delta_z = Complex::new(0., 0.);
+            // Derivative of the full value of z with respect to c
+            let mut derivative = Complex::new(0., 0.);
+            let mut tracker = trap.map(TrapTracker::new);
+            let mut m = 0;
+
+            let values = |escape: Escape, distance: f64, tracker: &Option<TrapTracker>| {
+                let mut values = vec![];
+                if distance_estimation {
+                    values.push(distance / pixel_width);
+                }
+                let (color, trap_iteration) =
+                    tracker.as_ref().map_or((0., 0.), TrapTracker::values);
+                if tracker.is_some() {
+                    values.push(trap_iteration);
+                }
+                (escape, color, values)
+            };
+
+            for i in 0..iterations {
+                let z = reference[m] + delta_z;
+                if z.norm() > bound {
+                    let escape = if smooth {
+                        smooth_iteration_count(i, z, 2.)
+                    } else {
+                        i as f64
+                    };
+                    let distance = if distance_estimation {
+                        exterior_distance_estimate(z, derivative)
+                    } else {
+                        0.
+                    };
+                    return values(Escape::Escaped(escape), distance, &tracker);
+                }
+
+                // The delta became larger than the full value (a glitch, the precision of the
+                // delta being lost), or the reference orbit ended: rebase on the start of the
+                // reference orbit, the full value becoming the new delta
+                if z.norm_sqr() < delta_z.norm_sqr() || m == reference.len() - 1 {
+                    delta_z = z;
+                    m = 0;
+                    rebases.fetch_add(1, Ordering::Relaxed);
+                }
+                if distance_estimation {
+                    derivative = 2. * z * derivative + 1.;
+                }
+
+                delta_z = (reference[m] * 2. + delta_z) * delta_z + delta_c;
+                m += 1;
+                if let Some(tracker) = &mut tracker {
+                    tracker.update(i + 1, reference[m] + delta_z);
+                }
+            }
+            values(Escape::Interior, 0., &tracker)
+        });
+        info!("{} rebases of the pixel orbits", rebases.into_inner());
+
+        let mut channel_names = vec![];
+        if distance_estimation {
+            channel_names.push(DISTANCE_CHANNEL);
+        }
+        if trap.is_some() {
+            channel_names.push(TRAP_ITERATION_CHANNEL);
+        }
+        escape_time_histogram_from_escapes(builder, &channel_names, escapes)
+    }
+}
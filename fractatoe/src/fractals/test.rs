@@ -0,0 +1,1227 @@
+use std::collections::BTreeMap;
+
+use num::complex::Complex;
+
+use super::formula::expression::Expression;
+
+#[test]
+fn test_formula_evaluation() {
+    let mut parameters = BTreeMap::new();
+    parameters.insert("a".to_string(), (0.5, -1.));
+    let expression = Expression::parse("z^2 + c*sin(z) - a/2 + 2^-1", &parameters).unwrap();
+
+    let (z, c) = (Complex::new(0.3, -0.7), Complex::new(-1.2, 0.4));
+    let expected = z * z + c * z.sin() - Complex::new(0.5, -1.) / 2. + Complex::new(0.5, 0.);
+    let value = expression.evaluate(z, c, &mut expression.new_stack());
+    assert!((value - expected).norm() < 1e-12);
+}
+
+#[test]
+fn test_formula_errors() {
+    let parameters = BTreeMap::new();
+    assert!(Expression::parse("z^2 + d", &parameters).is_err());
+    assert!(Expression::parse("z^2 + ", &parameters).is_err());
+    assert!(Expression::parse("sin z", &parameters).is_err());
+    assert!(Expression::parse("(z + c", &parameters).is_err());
+    assert!(Expression::parse("z # c", &parameters).is_err());
+}
+
+#[test]
+fn test_deep_zoom_matches_direct_iteration() {
+    use super::histogram::HistogramBuilder;
+    use super::mandelbrot::Mandelbrot;
+    use super::HistogramGeneration;
+
+    let builder = || -> HistogramBuilder {
+        serde_json::from_str(
+            r#"{"width_px": 100, "height_px": 100, "resolution_px": 1,
+                "center": ["-0.7436438870", "0.1318259042"],
+                "width_real": 1e-6, "height_real": 1e-6}"#,
+        )
+        .unwrap()
+    };
+    let direct: Mandelbrot = serde_json::from_str(r#"{"bound": 4.0, "iterations": 2000}"#).unwrap();
+    let deep_zoom: Mandelbrot = serde_json::from_str(
+        r#"{"bound": 4.0, "iterations": 2000,
+            "deep_zoom": {}}"#,
+    )
+    .unwrap();
+
+    let direct = direct.build().unwrap().build_histogram(builder());
+    let deep_zoom = deep_zoom.build().unwrap().build_histogram(builder());
+
+    let mut equal_cells = 0;
+    for x in 0..100 {
+        for y in 0..100 {
+            if direct.get_cell(x, y) == deep_zoom.get_cell(x, y) {
+                equal_cells += 1;
+            }
+        }
+    }
+    assert!(equal_cells > 9900, "{} equal cells", equal_cells);
+}
+
+#[test]
+fn test_deep_zoom_orbit_trap() {
+    use super::histogram::HistogramBuilder;
+    use super::mandelbrot::Mandelbrot;
+    use super::orbit_trap::TRAP_ITERATION_CHANNEL;
+    use super::HistogramGeneration;
+
+    let builder = || -> HistogramBuilder {
+        serde_json::from_str(
+            r#"{"width_px": 50, "height_px": 50, "resolution_px": 1,
+                "center": ["-0.7436438870", "0.1318259042"],
+                "width_real": 1e-6, "height_real": 1e-6}"#,
+        )
+        .unwrap()
+    };
+    let mandelbrot = |deep_zoom: &str| -> Mandelbrot {
+        serde_json::from_str(&format!(
+            r#"{{"bound": 4.0, "iterations": 2000, "deep_zoom": {},
+                "orbit_trap": {{"Circle": {{"center": [0, 0], "radius": 1}}}}}}"#,
+            deep_zoom
+        ))
+        .unwrap()
+    };
+
+    let direct = mandelbrot("null")
+        .build()
+        .unwrap()
+        .build_histogram(builder());
+    let deep_zoom = mandelbrot("{}").build().unwrap().build_histogram(builder());
+    let channel = direct.channel_index(TRAP_ITERATION_CHANNEL).unwrap();
+    assert_eq!(
+        deep_zoom.channel_index(TRAP_ITERATION_CHANNEL),
+        Some(channel)
+    );
+
+    let mut equal_cells = 0;
+    for x in 0..50 {
+        for y in 0..50 {
+            let (color, deep_zoom_color) = (direct.get_cell(x, y).1, deep_zoom.get_cell(x, y).1);
+            if (color - deep_zoom_color).abs() < 1e-6
+                && direct.get_channel_cell(channel, x, y)
+                    == deep_zoom.get_channel_cell(channel, x, y)
+            {
+                equal_cells += 1;
+            }
+        }
+    }
+    assert!(equal_cells > 2450, "{} equal cells", equal_cells);
+}
+
+#[test]
+fn test_precise_viewport_serialization() {
+    use super::histogram::HistogramBuilder;
+
+    let center = "-1.74995768370609350360221450607069970727110579726252077930242837820286008082";
+    let builder: HistogramBuilder = serde_json::from_str(&format!(
+        r#"{{"width_px": 10, "height_px": 10, "resolution_px": 1,
+            "center": ["{}", 0], "width_real": "1e-70", "height_real": 1e-70}}"#,
+        center
+    ))
+    .unwrap();
+
+    assert_eq!(builder.precise_center().0.to_string(), center);
+    assert!((builder.center().0 + 1.7499576837060935).abs() < 1e-15);
+    assert_eq!(builder.width_real(), 1e-70);
+
+    let serialized = serde_json::to_value(&builder).unwrap();
+    assert_eq!(serialized["center"][0], center);
+    assert_eq!(serialized["center"][1], 0.);
+    assert_eq!(serialized["width_real"], "1e-70");
+
+    assert!(serde_json::from_str::<HistogramBuilder>(
+        r#"{"width_px": 10, "height_px": 10, "resolution_px": 1,
+            "center": ["-1.7a", 0], "width_real": 1, "height_real": 1}"#
+    )
+    .is_err());
+}
+
+#[test]
+fn test_mandelbrot_interior_checks_keep_results() {
+    use super::escape_time::{escape_time_histogram, iterate_until_escape};
+    use super::histogram::HistogramBuilder;
+    use super::mandelbrot::Mandelbrot;
+    use super::HistogramGeneration;
+
+    for &(center, width, smooth) in &[("[-0.5, 0]", 3., false), ("[-0.75, 0.1]", 0.05, true)] {
+        let builder = || -> HistogramBuilder {
+            serde_json::from_str(&format!(
+                r#"{{"width_px": 120, "height_px": 120, "resolution_px": 1,
+                    "center": {}, "width_real": {}, "height_real": {}}}"#,
+                center, width, width
+            ))
+            .unwrap()
+        };
+        let mandelbrot: Mandelbrot = serde_json::from_str(&format!(
+            r#"{{"bound": 4.0, "iterations": 3000, "smooth": {}}}"#,
+            smooth
+        ))
+        .unwrap();
+
+        let naive = escape_time_histogram(&builder(), |(x, y)| {
+            let c = Complex::new(x, y);
+            let degree = if smooth { Some(2.) } else { None };
+            iterate_until_escape(Complex::new(0., 0.), 0, |z| z * z + c, 4., 3000, degree)
+        });
+        assert!(mandelbrot.build().unwrap().build_histogram(builder()) == naive);
+    }
+}
+
+#[test]
+fn test_julia_distance_estimation() {
+    use super::escape_time::DISTANCE_CHANNEL;
+    use super::histogram::HistogramBuilder;
+    use super::julia::Julia;
+    use super::HistogramGeneration;
+
+    // The Julia set of z^2 is the unit circle, the estimate at radius r being r ln(r)
+    let builder: HistogramBuilder = serde_json::from_str(
+        r#"{"width_px": 40, "height_px": 40, "resolution_px": 1,
+            "center": [0, 0], "width_real": 4, "height_real": 4}"#,
+    )
+    .unwrap();
+    let pixel_width = builder.pixel_width_real();
+    let (x0, y0) = builder.point_top_left();
+    let julia: Julia = serde_json::from_str(
+        r#"{"c": [0, 0], "bound": 1e10, "iterations": 100, "distance_estimation": true}"#,
+    )
+    .unwrap();
+    let histogram = julia.build().unwrap().build_histogram(builder);
+    let channel = histogram.channel_index(DISTANCE_CHANNEL).unwrap();
+
+    for x in 0..40 {
+        for y in 0..40 {
+            let r = Complex::new(x0 + x as f64 * pixel_width, y0 + y as f64 * pixel_width).norm();
+            let distance = histogram.get_channel_cell(channel, x, y) * pixel_width;
+            if r > 1.01 {
+                assert!(
+                    (distance - r * r.ln()).abs() < 1e-9,
+                    "{} at {}",
+                    distance,
+                    r
+                );
+            } else if r < 0.99 {
+                assert_eq!(distance, 0.);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_orbit_trap() {
+    use super::histogram::HistogramBuilder;
+    use super::julia::Julia;
+    use super::orbit_trap::TRAP_ITERATION_CHANNEL;
+    use super::HistogramGeneration;
+
+    // Outside of the unit circle, the orbits of z^2 come closest to 0 at their starting point
+    let builder: HistogramBuilder = serde_json::from_str(
+        r#"{"width_px": 40, "height_px": 40, "resolution_px": 1,
+            "center": [0, 0], "width_real": 4, "height_real": 4}"#,
+    )
+    .unwrap();
+    let pixel_width = builder.pixel_width_real();
+    let (x0, y0) = builder.point_top_left();
+    let julia: Julia = serde_json::from_str(
+        r#"{"c": [0, 0], "bound": 1e10, "iterations": 100,
+            "orbit_trap": {"Point": {"point": [0, 0]}}}"#,
+    )
+    .unwrap();
+    let histogram = julia.build().unwrap().build_histogram(builder);
+    let channel = histogram.channel_index(TRAP_ITERATION_CHANNEL).unwrap();
+
+    for x in 0..40 {
+        for y in 0..40 {
+            let r = Complex::new(x0 + x as f64 * pixel_width, y0 + y as f64 * pixel_width).norm();
+            if r > 1.01 {
+                let (_, color) = histogram.get_cell(x, y);
+                assert!((color - r).abs() < 1e-9);
+                assert_eq!(histogram.get_channel_cell(channel, x, y), 1.);
+            }
+        }
+    }
+
+    // A missing image is reported when the generator is built
+    let image_trap: Julia = serde_json::from_str(
+        r#"{"c": [0, 0], "bound": 1e10, "iterations": 100,
+            "orbit_trap": {"Image": {"filename": "missing.png", "center": [0, 0],
+                                     "width": 1, "height": 1}}}"#,
+    )
+    .unwrap();
+    assert!(image_trap.build().is_err());
+}
+
+#[test]
+fn test_orbit_trap_reduced_resolution() {
+    use super::histogram::{FrequencyAggregationType, HistogramBuilder};
+    use super::mandelbrot::Mandelbrot;
+    use super::HistogramGeneration;
+
+    // The trap distance of a pixel is the mean one of its virtual pixels, whether they escape
+    // or not (the interior points having a null frequency)
+    let histogram = || {
+        let builder: HistogramBuilder = serde_json::from_str(
+            r#"{"width_px": 8, "height_px": 8, "resolution_px": 2,
+                "center": [0.25, 0.5], "width_real": 1, "height_real": 1}"#,
+        )
+        .unwrap();
+        let mandelbrot: Mandelbrot = serde_json::from_str(
+            r#"{"bound": 4, "iterations": 200, "orbit_trap": {"Point": {"point": [1, 0]}}}"#,
+        )
+        .unwrap();
+        mandelbrot.build().unwrap().build_histogram(builder)
+    };
+    let (histogram, mut reduced) = (histogram(), histogram());
+    reduced.reduce_resolution(FrequencyAggregationType::Linear);
+
+    let mut interior_pixels = 0;
+    for x in 0..8 {
+        for y in 0..8 {
+            let cells: Vec<(f64, f64)> = (0..4)
+                .map(|i| histogram.get_cell(2 * x + i % 2, 2 * y + i / 2))
+                .collect();
+            let mean = cells.iter().map(|(_, color)| color).sum::<f64>() / 4.;
+            let (_, color) = reduced.get_cell(x, y);
+            assert!((color - mean).abs() < 1e-12);
+            if cells.iter().all(|&(freq, _)| freq == 0.) {
+                interior_pixels += 1;
+                assert!(color > 0.);
+            }
+        }
+    }
+    assert!(interior_pixels > 0);
+}
+
+#[test]
+fn test_flat_flame_conf_conversion() {
+    use super::flame::FlameConf;
+
+    let flat: FlameConf = serde_json::from_str(
+        r#"{"variation_functions": ["Linear", "Swirl"], "weight_variation": [0.7, 0.3],
+            "flame_distribution": [1, 2],
+            "coefs_inside": [[0.5, 0, 0, 0, 0.5, 0], [0.5, 0, 0.5, 0, 0.5, 0]],
+            "number_points": 10, "iteration_offset": 20, "number_iterations": 100,
+            "seed": "flat"}"#,
+    )
+    .unwrap();
+    let variations = r#"[{"variation": "Linear", "weight": 0.7},
+                         {"variation": "Swirl", "weight": 0.3}]"#;
+    let xforms: FlameConf = serde_json::from_str(&format!(
+        r#"{{"xforms": [
+                {{"weight": 1, "coefs": [0.5, 0, 0, 0, 0.5, 0], "variations": {},
+                  "color": 0, "color_speed": 0}},
+                {{"weight": 2, "coefs": [0.5, 0, 0.5, 0, 0.5, 0], "variations": {},
+                  "color": 0, "color_speed": 0}}],
+            "number_points": 10, "iteration_offset": 20, "number_iterations": 100,
+            "seed": "flat"}}"#,
+        variations, variations
+    ))
+    .unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&flat).unwrap(),
+        serde_json::to_value(&xforms).unwrap()
+    );
+
+    // A transform without weight is an error rather than being dropped
+    assert!(serde_json::from_str::<FlameConf>(
+        r#"{"variation_functions": ["Linear", "Swirl"], "weight_variation": [0.7, 0.3],
+            "flame_distribution": [1],
+            "coefs_inside": [[0.5, 0, 0, 0, 0.5, 0], [0.5, 0, 0.5, 0, 0.5, 0]],
+            "number_points": 10, "iteration_offset": 20, "number_iterations": 100,
+            "seed": "flat"}"#,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_flame_final_xform_is_not_iterated() {
+    use super::flame::FlameConf;
+    use super::histogram::HistogramBuilder;
+    use super::HistogramGeneration;
+
+    let builder = || -> HistogramBuilder {
+        serde_json::from_str(
+            r#"{"width_px": 20, "height_px": 20, "resolution_px": 1,
+                "center": [0.5, 0.5], "width_real": 1, "height_real": 1}"#,
+        )
+        .unwrap()
+    };
+    let flame = |post: &str, final_xform: &str| -> FlameConf {
+        serde_json::from_str(&format!(
+            r#"{{"xforms": [{{"coefs": [0.5, 0, 0, 0, 0.5, 0], "post": {},
+                              "variations": [{{"variation": "Linear", "weight": 1}}],
+                              "color": 0}}],
+                "final_xform": {},
+                "number_points": 10, "iteration_offset": 20, "number_iterations": 100,
+                "seed": "final"}}"#,
+            post, final_xform
+        ))
+        .unwrap()
+    };
+    // Number of points plotted around (0.5, 0.5)
+    let center = |histogram: super::histogram::Histogram| -> f64 {
+        (9..11)
+            .flat_map(|x| (9..11).map(move |y| (x, y)))
+            .map(|(x, y)| histogram.get_cell(x, y).0)
+            .sum()
+    };
+
+    // The post-affine map is iterated: the points converge to the fixed point (0.5, 0.5)
+    let post = flame("[1, 0, 0.25, 0, 1, 0.25]", "null");
+    assert!(center(post.build().unwrap().build_histogram(builder())) > 900.);
+
+    // The final transform only moves the plotted points: the orbit still converges to (0, 0),
+    // plotted at (0.5, 0.5)
+    let final_xform = flame(
+        "null",
+        r#"{"coefs": [1, 0, 0.5, 0, 1, 0.5],
+            "variations": [{"variation": "Linear", "weight": 1}], "color": 0}"#,
+    );
+    assert!(center(final_xform.build().unwrap().build_histogram(builder())) > 900.);
+
+    // Without it, the points are plotted in the corner of the viewport
+    let plain = flame("null", "null");
+    assert!(center(plain.build().unwrap().build_histogram(builder())) < 100.);
+}
+
+#[test]
+fn test_flame_xaos() {
+    use super::flame::FlameConf;
+    use super::histogram::HistogramBuilder;
+    use super::HistogramGeneration;
+
+    let builder: HistogramBuilder = serde_json::from_str(
+        r#"{"width_px": 20, "height_px": 20, "resolution_px": 1,
+            "center": [0.5, 0.5], "width_real": 1, "height_real": 1}"#,
+    )
+    .unwrap();
+    // Once the first transform is applied, only it can follow: the orbits converge to its fixed
+    // point (0, 0)
+    let flame: FlameConf = serde_json::from_str(
+        r#"{"xforms": [{"coefs": [0.5, 0, 0, 0, 0.5, 0], "color": 0, "xaos": [1, 0],
+                        "variations": [{"variation": "Linear", "weight": 1}]},
+                       {"coefs": [0.5, 0, 0.5, 0, 0.5, 0.5], "color": 1,
+                        "variations": [{"variation": "Linear", "weight": 1}]}],
+            "number_points": 10, "iteration_offset": 100, "number_iterations": 100,
+            "seed": "xaos"}"#,
+    )
+    .unwrap();
+    let histogram = flame.build().unwrap().build_histogram(builder);
+    assert_eq!(histogram.get_cell(0, 0).0, 1000.);
+
+    // A transform allowing no successor is followed by any of them, as if it had no xaos, while
+    // negative multipliers are rejected
+    let flame = |xaos: &str| -> FlameConf {
+        serde_json::from_str(&format!(
+            r#"{{"xforms": [{{"coefs": [0.5, 0, 0, 0, 0.5, 0], "color": 0, "xaos": {},
+                              "variations": [{{"variation": "Linear", "weight": 1}}]}},
+                             {{"coefs": [0.5, 0, 0.5, 0, 0.5, 0.5], "color": 1,
+                              "variations": [{{"variation": "Linear", "weight": 1}}]}}],
+                "number_points": 10, "iteration_offset": 100, "number_iterations": 100,
+                "seed": "xaos"}}"#,
+            xaos
+        ))
+        .unwrap()
+    };
+    let builder = || HistogramBuilder::new(20, 20, 1, (0.5, 0.5), 1., 1.);
+    assert!(
+        flame("[0, 0]").build().unwrap().build_histogram(builder())
+            == flame("null").build().unwrap().build_histogram(builder())
+    );
+    assert!(flame("[1, -1]").build().is_err());
+}
+
+#[test]
+fn test_flame_symmetry() {
+    use super::flame::flam3::{from_flam3, to_flam3};
+    use super::flame::FlameConf;
+    use super::histogram::HistogramBuilder;
+    use super::HistogramGeneration;
+
+    let builder = || -> HistogramBuilder {
+        serde_json::from_str(
+            r#"{"width_px": 20, "height_px": 20, "resolution_px": 1,
+                "center": [0, 0], "width_real": 2, "height_real": 2}"#,
+        )
+        .unwrap()
+    };
+    let flame = |symmetry: &str| -> FlameConf {
+        serde_json::from_str(&format!(
+            r#"{{"xforms": [{{"coefs": [0.4, 0, 0.5, 0, 0.4, 0.5], "color": 0,
+                              "variations": [{{"variation": "Linear", "weight": 1}}]}}],
+                "symmetry": {},
+                "number_points": 100, "iteration_offset": 20, "number_iterations": 100,
+                "seed": "symmetry"}}"#,
+            symmetry
+        ))
+        .unwrap()
+    };
+    // Number of points plotted in each quadrant of the viewport
+    let quadrants = |histogram: super::histogram::Histogram| -> Vec<f64> {
+        [(0, 0), (10, 0), (0, 10), (10, 10)]
+            .iter()
+            .map(|&(x0, y0)| {
+                (x0..x0 + 10)
+                    .flat_map(|x| (y0..y0 + 10).map(move |y| (x, y)))
+                    .map(|(x, y)| histogram.get_cell(x, y).0)
+                    .sum()
+            })
+            .collect()
+    };
+
+    // Without symmetry, the attractor is in a single quadrant
+    let plain = quadrants(flame("null").build().unwrap().build_histogram(builder()));
+    assert_eq!(plain.iter().filter(|&&count| count > 0.).count(), 1);
+
+    // The rotations by a quarter turn spread it in the four quadrants, the original one being
+    // denser as the orbits come back to it after each application of the transform
+    let rotational = quadrants(
+        flame(r#"{"Rotational": 4}"#)
+            .build()
+            .unwrap()
+            .build_histogram(builder()),
+    );
+    assert!(rotational[3] > rotational[0]);
+    for &count in rotational[..3].iter() {
+        assert!((count - rotational[0]).abs() < 0.1 * rotational[0]);
+    }
+
+    let (builder, dihedral) = from_flam3(
+        "<flame size=\"10 10\" symmetry=\"-3\"><xform coefs=\"1 0 0 1 0 0\" linear=\"1\"/></flame>",
+    )
+    .unwrap();
+    assert_eq!(
+        serde_json::to_value(&dihedral).unwrap()["symmetry"],
+        serde_json::json!({"Dihedral": 3})
+    );
+    assert!(to_flam3(&builder, &dihedral)
+        .unwrap()
+        .contains("symmetry=\"-3\""));
+}
+
+#[test]
+fn test_flame_runaway_points_are_reseeded() {
+    use super::flame::FlameConf;
+    use super::histogram::HistogramBuilder;
+    use super::HistogramGeneration;
+
+    let builder: HistogramBuilder = serde_json::from_str(
+        r#"{"width_px": 10, "height_px": 10, "resolution_px": 1,
+            "center": [0.5, 0.5], "width_real": 1, "height_real": 1}"#,
+    )
+    .unwrap();
+    // The first transform sends every point to the singularity of the spherical variation
+    let flame: FlameConf = serde_json::from_str(
+        r#"{"xforms": [{"coefs": [0, 0, 0, 0, 0, 0], "color": 0,
+                        "variations": [{"variation": "Spherical", "weight": 1}]},
+                       {"coefs": [0.5, 0, 0.25, 0, 0.5, 0.25], "color": 1,
+                        "variations": [{"variation": "Linear", "weight": 1}]}],
+            "number_points": 10, "iteration_offset": 20, "number_iterations": 100,
+            "seed": "runaway"}"#,
+    )
+    .unwrap();
+    let histogram = flame.build().unwrap().build_histogram(builder);
+
+    let cells: Vec<f64> = (0..10)
+        .flat_map(|x| (0..10).map(move |y| (x, y)))
+        .map(|(x, y)| histogram.get_cell(x, y).0)
+        .collect();
+    // Every point is plotted in the viewport, and the NaN ones don't pile up in a corner
+    assert_eq!(cells.iter().sum::<f64>(), 1000.);
+    assert!(cells[0] < 50.);
+}
+
+#[test]
+fn test_flame_palette() {
+    use super::flame::FlameConf;
+    use super::histogram::{HistogramBuilder, GREEN_CHANNEL, RED_CHANNEL};
+    use super::HistogramGeneration;
+
+    let builder = || -> HistogramBuilder {
+        serde_json::from_str(
+            r#"{"width_px": 10, "height_px": 10, "resolution_px": 1,
+                "center": [0.5, 0.5], "width_real": 1, "height_real": 1}"#,
+        )
+        .unwrap()
+    };
+    // The points take the color 1 of the transform at its first application
+    let flame = |palette: &str| -> FlameConf {
+        serde_json::from_str(&format!(
+            r#"{{"xforms": [{{"coefs": [0.5, 0, 0.25, 0, 0.5, 0.25], "color": 1,
+                              "color_speed": 1,
+                              "variations": [{{"variation": "Linear", "weight": 1}}]}}],
+                "palette": {},
+                "number_points": 10, "iteration_offset": 20, "number_iterations": 100,
+                "seed": "palette"}}"#,
+            palette
+        ))
+        .unwrap()
+    };
+    let color_sums = |histogram: super::histogram::Histogram| -> (f64, f64, f64) {
+        let (red, green) = (
+            histogram.channel_index(RED_CHANNEL).unwrap(),
+            histogram.channel_index(GREEN_CHANNEL).unwrap(),
+        );
+        (0..10)
+            .flat_map(|x| (0..10).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                (
+                    histogram.get_cell(x, y).0,
+                    histogram.get_channel_cell(red, x, y),
+                    histogram.get_channel_cell(green, x, y),
+                )
+            })
+            .fold((0., 0., 0.), |sums, cell| {
+                (sums.0 + cell.0, sums.1 + cell.1, sums.2 + cell.2)
+            })
+    };
+
+    let red = flame(r#"{"source": {"Colors": [[0, 0, 0], [255, 0, 0]]}}"#);
+    assert_eq!(
+        color_sums(red.build().unwrap().build_histogram(builder())),
+        (1000., 1000., 0.)
+    );
+
+    // A third of turn changes red into green
+    let green = flame(
+        r#"{"source": {"Colors": [[0, 0, 0], [255, 0, 0]]}, "hue_rotation": 0.3333333333333333}"#,
+    );
+    let (freq, red_sum, green_sum) = color_sums(green.build().unwrap().build_histogram(builder()));
+    assert!(red_sum.abs() < 1e-9 && (green_sum - freq).abs() < 1e-9);
+
+    let named = flame(r#"{"source": {"Named": "greyscale"}}"#);
+    let (freq, red_sum, green_sum) = color_sums(named.build().unwrap().build_histogram(builder()));
+    assert!((red_sum - freq).abs() < 1e-9 && (green_sum - freq).abs() < 1e-9);
+
+    // An unknown palette or a missing map file is an error of the configuration
+    assert!(flame(r#"{"source": {"Named": "unknown"}}"#)
+        .build()
+        .is_err());
+    assert!(flame(r#"{"source": {"File": "missing.map"}}"#)
+        .build()
+        .is_err());
+}
+
+#[test]
+fn test_flame_mean_color() {
+    use super::flame::FlameConf;
+    use super::histogram::{FrequencyAggregationType, HistogramBuilder};
+    use super::HistogramGeneration;
+
+    let builder: HistogramBuilder = serde_json::from_str(
+        r#"{"width_px": 5, "height_px": 5, "resolution_px": 2,
+            "center": [0.5, 0.5], "width_real": 1, "height_real": 1}"#,
+    )
+    .unwrap();
+    // Both transforms converge to (0.55, 0.55), the second one coloring a quarter of the points
+    let flame: FlameConf = serde_json::from_str(
+        r#"{"xforms": [{"weight": 3, "coefs": [0.5, 0, 0.275, 0, 0.5, 0.275], "color": 0,
+                        "color_speed": 1,
+                        "variations": [{"variation": "Linear", "weight": 1}]},
+                       {"weight": 1, "coefs": [0.5, 0, 0.275, 0, 0.5, 0.275], "color": 1,
+                        "color_speed": 1,
+                        "variations": [{"variation": "Linear", "weight": 1}]}],
+            "palette": {"source": {"Colors": [[0, 0, 0], [255, 255, 255]]}},
+            "number_points": 100, "iteration_offset": 20, "number_iterations": 100,
+            "seed": "mean color"}"#,
+    )
+    .unwrap();
+    let mut histogram = flame.build().unwrap().build_histogram(builder);
+
+    let (freq, color) = histogram.get_cell(5, 5);
+    assert_eq!(freq, 10000.);
+    assert!((color - 0.25).abs() < 0.02, "{}", color);
+    let channels = histogram.color_channels().unwrap();
+    let red_sum = histogram.get_color_sum(channels, 5, 5)[0];
+    assert!((red_sum / freq - color).abs() < 1e-9);
+
+    // The colors are still the mean ones, and their sums are kept, in the reduced histogram
+    histogram.reduce_density_resolution(FrequencyAggregationType::Linear);
+    assert_eq!(histogram.get_cell(2, 2), (1., color));
+    assert_eq!(histogram.get_color_sum(channels, 2, 2)[0], red_sum);
+}
+
+#[test]
+fn test_flame_variations() {
+    use rand::{Rng, SeedableRng};
+
+    use super::flame::variations::VariationFunction;
+
+    let variations: Vec<VariationFunction> = serde_json::from_str(
+        r#"["Bisin", "Linear", "Sinusoidal", "Spherical", "Swirl", "Horseshoe", "Polar",
+            "Handkerchief", "Heart", "Disc", "Spiral", "Hyperbolic", "Diamond", "Ex", "Julia",
+            "Flam3Julia",
+            "Bent", "Waves", "Fisheye", "Popcorn", "Exponential", "Power", "Cosine", "Rings",
+            "Fan", {"Blob": {"high": 1, "low": 0.5, "waves": 3}},
+            {"Pdj": {"a": 1, "b": 2, "c": 3, "d": 4}}, {"Fan2": {"x": 0.5, "y": 1}},
+            {"Rings2": {"val": 0.5}}, "Eyefish", "Bubble", "Cylinder",
+            {"Perspective": {"angle": 0.5, "dist": 2}}, "Noise",
+            {"Julian": {"power": 3, "dist": 1}}, {"Juliascope": {"power": -2, "dist": 0.5}},
+            "Blur", "GaussianBlur", {"RadialBlur": {"angle": 0.3}},
+            {"Pie": {"slices": 6, "rotation": 0.5, "thickness": 0.5}},
+            {"Ngon": {"power": 2, "sides": 5, "corners": 1, "circle": 1}},
+            {"Curl": {"c1": 0.5, "c2": 0.2}}, {"Rectangles": {"x": 0.3, "y": 0.4}}, "Arch",
+            "Tangent", "Square", "Rays", "Blade", "Secant2", "Twintrian", "Cross"]"#,
+    )
+    .unwrap();
+    let coefs = (0.5, 0.1, 0.3, -0.2, 0.6, 0.4);
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+
+    for variation in variations {
+        let function = variation.build(0.7, coefs);
+        for _ in 0..100 {
+            let (x, y) = (rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+            let (x_image, y_image) = function(x, y, &mut rng);
+            assert!(x_image.is_finite() && y_image.is_finite());
+        }
+    }
+
+    let polar = VariationFunction::Polar.build(1., coefs);
+    assert_eq!(polar(0., 2., &mut rng), (0., 1.));
+
+    // The original julia variation always takes the root of angle atan(x/y) / 2, while the flam3
+    // one takes either square root of y + ix
+    let julia = VariationFunction::Julia.build(1., coefs);
+    let (x, y) = julia(0.3, -0.4, &mut rng);
+    let (r, angle) = (0.5f64.sqrt(), (-0.75f64).atan() / 2.);
+    assert!((x - r * angle.cos()).abs() < 1e-12 && (y - r * angle.sin()).abs() < 1e-12);
+    let flam3_julia = VariationFunction::Flam3Julia.build(1., coefs);
+    let roots: Vec<Complex<f64>> = (0..20)
+        .map(|_| {
+            let (x, y) = flam3_julia(0.3, -0.4, &mut rng);
+            Complex::new(x, y)
+        })
+        .collect();
+    for root in roots.iter() {
+        assert!((root * root - Complex::new(-0.4, 0.3)).norm() < 1e-12);
+    }
+    assert!(roots.iter().any(|root| root.re > 0.) && roots.iter().any(|root| root.re < 0.));
+
+    let julian: VariationFunction =
+        serde_json::from_str(r#"{"Julian": {"power": 1, "dist": 1}}"#).unwrap();
+    let (x, y) = julian.build(1., coefs)(0.3, -0.4, &mut rng);
+    assert!((x - 0.3).abs() < 1e-12 && (y + 0.4).abs() < 1e-12);
+}
+
+#[test]
+fn test_flame_parametric_variations() {
+    use rand::SeedableRng;
+
+    use super::flame::variations::VariationFunction;
+
+    // Weighted images of (0.3, -0.4) given by the formulas of flam3, with the weight 0.7 and the
+    // random numbers 0.9742447, 0.6912356, 0.4279748, 0.1755861 drawn from the seed 1
+    let references = [
+        (
+            r#"{"Blob": {"high": 1, "low": 0.5, "waves": 3}}"#,
+            (0.20664, -0.27552),
+        ),
+        (
+            r#"{"Pdj": {"a": 1, "b": 2, "c": 3, "d": 4}}"#,
+            (-0.8503277700528301, 0.5687685023501404),
+        ),
+        (
+            r#"{"Fan2": {"x": 0.5, "y": 1}}"#,
+            (0.08686334071188725, -0.3390497899134736),
+        ),
+        (r#"{"Rings2": {"val": 0.5}}"#, (0.1575, -0.21)),
+        (
+            r#"{"Perspective": {"angle": 0.5, "dist": 2}}"#,
+            (0.18398113794914797, -0.1734590803390136),
+        ),
+        (
+            r#"{"Julian": {"power": 3, "dist": 1}}"#,
+            (-0.4109973899637322, -0.3738472985956216),
+        ),
+        (
+            r#"{"Juliascope": {"power": -2, "dist": 0.5}}"#,
+            (-0.7445614255722984, 0.3722807127861491),
+        ),
+        (
+            r#"{"RadialBlur": {"angle": 0.3}}"#,
+            (0.08340295357005334, -0.04004113261574049),
+        ),
+        (
+            r#"{"Pie": {"slices": 6, "rotation": 0.5, "thickness": 0.5}}"#,
+            (0.1950201771450742, 0.22741308929803677),
+        ),
+        (
+            r#"{"Ngon": {"power": 2, "sides": 5, "corners": 1, "circle": 1}}"#,
+            (0.8877095877064327, -1.1836127836085768),
+        ),
+        (
+            r#"{"Curl": {"c1": 0.5, "c2": 0.2}}"#,
+            (0.22781065088757393, -0.19674556213017752),
+        ),
+        (r#"{"Rectangles": {"x": 0.3, "y": 0.4}}"#, (0.42, 0.)),
+        (r#""Arch""#, (0.5886953387319779, -0.9150596160135197)),
+        (r#""Rays""#, (-2.910524297975736, 1.1864003524079159)),
+        (r#""Blade""#, (0.2681367500922883, 0.12768196133340728)),
+        (
+            r#""Twintrian""#,
+            (-0.0018899948701299428, -0.22251586113333338),
+        ),
+    ];
+
+    let coefs = (0.5, 0.1, 0.3, -0.2, 0.6, 0.4);
+    for (variation, (x_reference, y_reference)) in references.iter() {
+        let weight = 0.7;
+        let function = serde_json::from_str::<VariationFunction>(variation)
+            .unwrap()
+            .build(weight, coefs);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let (x, y) = function(0.3, -0.4, &mut rng);
+        assert!(
+            (weight * x - x_reference).abs() < 1e-8 && (weight * y - y_reference).abs() < 1e-8,
+            "{}: ({}, {}) instead of ({}, {})",
+            variation,
+            weight * x,
+            weight * y,
+            x_reference,
+            y_reference
+        );
+    }
+}
+
+#[test]
+fn test_flam3_round_trip() {
+    use super::flame::flam3::{from_flam3, to_flam3};
+    use super::flame::FlameConf;
+
+    let (builder, flame) = from_flam3(include_str!("../../../samples/apophysis.flame")).unwrap();
+    assert!(
+        (builder.width_real() - 4.).abs() < 1e-12 && (builder.height_real() - 3.).abs() < 1e-12
+    );
+    let flame_json = serde_json::to_value(&flame).unwrap();
+    assert_eq!(flame_json["xforms"].as_array().unwrap().len(), 3);
+    assert_eq!(flame_json["xforms"][0]["color_speed"], 0.5);
+    assert_eq!(flame_json["xforms"][1]["post"][2], 0.1);
+    assert_eq!(
+        flame_json["xforms"][2]["xaos"],
+        serde_json::json!([1., 1., 0.])
+    );
+    assert_eq!(
+        flame_json["palette"]["source"]["Colors"]
+            .as_array()
+            .unwrap()
+            .len(),
+        256
+    );
+
+    let exported = to_flam3(&builder, &flame).unwrap();
+    let (builder_again, flame_again) = from_flam3(&exported).unwrap();
+    assert_eq!(flame_json, serde_json::to_value(&flame_again).unwrap());
+    assert_eq!(builder.center(), builder_again.center());
+    assert!((builder.width_real() - builder_again.width_real()).abs() < 1e-12);
+    assert!((builder.height_real() - builder_again.height_real()).abs() < 1e-12);
+    assert_eq!(exported, to_flam3(&builder_again, &flame_again).unwrap());
+
+    // The variations missing from flam3 are not exported, unless an identical one replaces them
+    let flame = |variation: &str| -> FlameConf {
+        serde_json::from_str(&format!(
+            r#"{{"xforms": [{{"coefs": [0.5, 0, 0, 0, 0.5, 0], "color": 0,
+                              "variations": [{{"variation": "{}", "weight": 1}}]}}],
+                "number_points": 10, "iteration_offset": 20, "number_iterations": 100,
+                "seed": "export"}}"#,
+            variation
+        ))
+        .unwrap()
+    };
+    assert!(to_flam3(&builder, &flame("Julia")).is_err());
+    let julia = to_flam3(&builder, &flame("Flam3Julia")).unwrap();
+    assert!(julia.contains(" julia=\"1\""));
+    let bisin = to_flam3(&builder, &flame("Bisin")).unwrap();
+    assert!(bisin.contains(" sinusoidal=\"1\""));
+
+    assert!(from_flam3(
+        "<flame size=\"10 10\"><xform coefs=\"1 0 0 1 0 0\" unknown=\"1\"/></flame>"
+    )
+    .is_err());
+}
+
+#[test]
+fn test_histogram_file_round_trip() {
+    use super::histogram::Histogram;
+    use super::histogram_file::{read_histogram, write_histogram, HistogramFileOptions, Precision};
+
+    let mut histogram = Histogram::new(3, 2, 2);
+    for x in 0..6 {
+        for y in 0..4 {
+            histogram.set_cell(x, y, ((x * y) as f64, 0.1 * x as f64 + 0.01 * y as f64));
+        }
+    }
+    histogram.add_channel("interior", (0..24).map(|i| i as f64 / 3.).collect());
+    histogram.add_color_channels();
+
+    for &compression in [false, true].iter() {
+        let options = HistogramFileOptions {
+            precision: Precision::Double,
+            compression,
+        };
+        let mut file = vec![];
+        write_histogram(&histogram, options, &mut file).unwrap();
+        assert!(read_histogram(&file[..]).unwrap() == histogram);
+
+        // Truncated in the header, in the names of the channels and in the values (the end of a
+        // deflate stream may hold no value)
+        for &length in [12, 40, 50, file.len() / 2].iter() {
+            assert!(read_histogram(&file[..length]).is_err());
+        }
+        if !compression {
+            assert!(read_histogram(&file[..file.len() - 1]).is_err());
+        }
+    }
+
+    // Corrupt dimensions fail without overflowing or allocating from them
+    let header = |(width, height, resolution): (u64, u64, u64), channels: &[u8]| {
+        let mut file = b"FRACTHST\x01\0\0\0\0\0\0\0".to_vec();
+        for dimension in [width, height, resolution].iter() {
+            file.extend_from_slice(&dimension.to_le_bytes());
+        }
+        file.extend_from_slice(channels);
+        file
+    };
+    let no_channel = [0, 0, 0, 0];
+    assert!(read_histogram(&header((u64::MAX, u64::MAX, 2), &no_channel)[..]).is_err());
+    assert!(read_histogram(&header((1 << 32, 0, 1 << 32), &no_channel)[..]).is_err());
+    assert!(read_histogram(&header((1, 1, 0), &no_channel)[..]).is_err());
+    assert!(read_histogram(&header((1 << 20, 1 << 20, 1), &no_channel)[..]).is_err());
+    let huge_name = [1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff];
+    assert!(read_histogram(&header((1, 1, 1), &huge_name)[..]).is_err());
+    let duplicate = [2, 0, 0, 0, 1, 0, 0, 0, b'a', 1, 0, 0, 0, b'a'];
+    assert!(read_histogram(&header((1, 1, 1), &duplicate)[..]).is_err());
+
+    let options = HistogramFileOptions {
+        precision: Precision::Single,
+        compression: false,
+    };
+    let mut file = vec![];
+    write_histogram(&histogram, options, &mut file).unwrap();
+    let single = read_histogram(&file[..]).unwrap();
+    let channel = single.channel_index("interior").unwrap();
+    assert!((single.get_cell(5, 3).1 - 0.53).abs() < 1e-6);
+    assert!((single.get_channel_cell(channel, 1, 3) - 19. / 3.).abs() < 1e-5);
+
+    // The legacy json histograms are still read
+    let json = serde_json::to_vec(&histogram).unwrap();
+    assert!(read_histogram(&json[..]).unwrap() == serde_json::from_slice(&json).unwrap());
+    assert!(read_histogram(&b"FRACTHST\x02\0\0\0"[..]).is_err());
+}
+
+#[test]
+fn test_smooth_iteration_count() {
+    use super::escape_time::{iterate_until_escape, Escape};
+
+    // With the bound e^2, an orbit escaping at n has a norm between e^2 and about e^4, so that its
+    // smooth count lies in [n - 1, n] (up to the norm of c, at most 2 here)
+    let bound = std::f64::consts::E.powi(2);
+    let escape = |c: Complex<f64>, degree: Option<f64>| -> f64 {
+        match iterate_until_escape(Complex::new(0., 0.), 0, |z| z * z + c, bound, 1000, degree) {
+            Escape::Escaped(iteration) => iteration,
+            Escape::Interior => panic!("{} did not escape", c),
+        }
+    };
+
+    let mut previous: Option<(f64, f64)> = None;
+    let mut band_changes = 0;
+    for i in 0..20000 {
+        let c = Complex::new(0.5 + i as f64 * 1e-4, 0.3);
+        let (count, smooth) = (escape(c, None), escape(c, Some(2.)));
+        assert!(
+            count - 1.02 <= smooth && smooth <= count,
+            "{} at {}",
+            smooth,
+            c
+        );
+
+        // The smooth count does not jump where the integer count does
+        if let Some((previous_count, previous_smooth)) = previous {
+            assert!(
+                (smooth - previous_smooth).abs() < 0.02,
+                "{} at {}",
+                smooth,
+                c
+            );
+            if count != previous_count {
+                band_changes += 1;
+            }
+        }
+        previous = Some((count, smooth));
+    }
+    assert!(band_changes >= 2);
+}
+
+#[test]
+fn test_interior_points_are_flagged() {
+    use super::escape_time::INTERIOR_CHANNEL;
+    use super::histogram::HistogramBuilder;
+    use super::julia::Julia;
+    use super::HistogramGeneration;
+    use crate::rendering::mandelbrot_rendering::MandelbrotRendererConf;
+    use crate::rendering::HistogramRendering;
+
+    // The origin never escapes under z^2, while (1.5, 0) escapes before its first iteration: both
+    // have a null frequency
+    let builder: HistogramBuilder = serde_json::from_str(
+        r#"{"width_px": 2, "height_px": 2, "resolution_px": 1,
+            "center": [1.5, 1.5], "width_real": 3, "height_real": 3}"#,
+    )
+    .unwrap();
+    let julia: Julia =
+        serde_json::from_str(r#"{"c": [0, 0], "bound": 1, "iterations": 100}"#).unwrap();
+    let histogram = julia.build().unwrap().build_histogram(builder);
+    assert_eq!(histogram.get_cell(0, 0).0, 0.);
+    assert_eq!(histogram.get_cell(1, 0).0, 0.);
+
+    let channel = histogram.channel_index(INTERIOR_CHANNEL).unwrap();
+    assert_eq!(histogram.get_channel_cell(channel, 0, 0), 1.);
+    assert_eq!(histogram.get_channel_cell(channel, 1, 0), 0.);
+
+    let renderer: MandelbrotRendererConf = serde_json::from_str(
+        r#"{"r": 255, "g": 255, "b": 255, "gamma": 1, "frequency_agreg_type": "Linear",
+            "interior_color": [255, 0, 0]}"#,
+    )
+    .unwrap();
+    let image = renderer.build().render_image(histogram);
+    let (interior, exterior) = (&image.pixels[0], &image.pixels[1]);
+    assert_eq!((interior.r, interior.g, interior.b), (255, 0, 0));
+    assert_eq!((exterior.r, exterior.g, exterior.b), (0, 0, 0));
+}
+
+/// Escape count of the generator `conf` at the point, or None if it never escaped
+fn escape_at<G>(conf: &str, (x, y): (f64, f64)) -> Option<f64>
+where
+    G: super::HistogramGeneration + serde::de::DeserializeOwned,
+{
+    use super::escape_time::INTERIOR_CHANNEL;
+    use super::histogram::HistogramBuilder;
+
+    let generator: G = serde_json::from_str(conf).unwrap();
+    let histogram = generator.build_histogram(HistogramBuilder::new(1, 1, 1, (x, y), 0., 0.));
+    let interior = histogram.channel_index(INTERIOR_CHANNEL).unwrap();
+    if histogram.get_channel_cell(interior, 0, 0) == 1. {
+        None
+    } else {
+        Some(histogram.get_cell(0, 0).0)
+    }
+}
+
+#[test]
+fn test_multibrot_escape() {
+    use super::histogram::HistogramBuilder;
+    use super::mandelbrot::Mandelbrot;
+    use super::multibrot::Multibrot;
+    use super::HistogramGeneration;
+
+    let cubic = r#"{"exponent": 3, "bound": 2, "iterations": 100}"#;
+    assert_eq!(escape_at::<Multibrot>(cubic, (0., 0.)), None);
+    // 1, 2, 9
+    assert_eq!(escape_at::<Multibrot>(cubic, (1., 0.)), Some(3.));
+    // i, 0, i: periodic orbit
+    assert_eq!(escape_at::<Multibrot>(cubic, (0., 1.)), None);
+
+    // The orbits of z -> z + c grow linearly: there is no smooth iteration count
+    let linear = r#"{"exponent": 1, "bound": 2, "iterations": 100, "smooth": true}"#;
+    assert_eq!(escape_at::<Multibrot>(linear, (1., 0.)), Some(3.));
+
+    let builder = || HistogramBuilder::new(60, 60, 1, (-0.5, 0.), 3., 3.);
+    let quadratic: Multibrot =
+        serde_json::from_str(r#"{"exponent": 2, "bound": 4, "iterations": 200}"#).unwrap();
+    let mandelbrot: Mandelbrot =
+        serde_json::from_str(r#"{"bound": 4, "iterations": 200}"#).unwrap();
+    assert!(
+        quadratic.build_histogram(builder())
+            == mandelbrot.build().unwrap().build_histogram(builder())
+    );
+}
+
+#[test]
+fn test_burning_ship_escape() {
+    use super::burning_ship::BurningShip;
+    use super::multibrot::Multibrot;
+
+    let conf = r#"{"bound": 2, "iterations": 100}"#;
+    let mandelbrot = r#"{"exponent": 2, "bound": 2, "iterations": 100}"#;
+    assert_eq!(escape_at::<BurningShip>(conf, (0., 0.)), None);
+    // i, -1 + i, 3i, while i is in the Mandelbrot set
+    assert_eq!(escape_at::<BurningShip>(conf, (0., 1.)), Some(3.));
+    assert_eq!(escape_at::<Multibrot>(mandelbrot, (0., 1.)), None);
+
+    // The orbits of the real points stay real, where the absolute values change nothing
+    for &x in [-2.1, -1.9, -1.2, 0.3, 0.5].iter() {
+        assert_eq!(
+            escape_at::<BurningShip>(conf, (x, 0.)),
+            escape_at::<Multibrot>(mandelbrot, (x, 0.))
+        );
+    }
+}
+
+#[test]
+fn test_tricorn_escape() {
+    use super::tricorn::Tricorn;
+
+    let conf = r#"{"bound": 2, "iterations": 100}"#;
+    assert_eq!(escape_at::<Tricorn>(conf, (0., 0.)), None);
+    // i, -1 + i, 3i
+    assert_eq!(escape_at::<Tricorn>(conf, (0., 1.)), Some(3.));
+
+    // The tricorn is invariant under the rotations of a third of turn
+    let (cos, sin) = (-0.5, 3f64.sqrt() / 2.);
+    for &(x, y) in [(0.3, 0.5), (-0.9, 0.7), (0.4, -0.2), (1.5, 0.5)].iter() {
+        assert_eq!(
+            escape_at::<Tricorn>(conf, (x, y)),
+            escape_at::<Tricorn>(conf, (cos * x - sin * y, sin * x + cos * y))
+        );
+    }
+}
+
+#[test]
+fn test_formula_matches_built_in_fractals() {
+    use super::formula::FormulaConf;
+    use super::histogram::HistogramBuilder;
+    use super::julia::Julia;
+    use super::mandelbrot::Mandelbrot;
+    use super::HistogramGeneration;
+
+    let builder = || HistogramBuilder::new(60, 60, 1, (-0.5, 0.), 3., 3.);
+    let formula = |julia_c: &str, initial_z: &str| -> FormulaConf {
+        serde_json::from_str(&format!(
+            r#"{{"formula": "z*z + c", "julia_c": {}, "initial_z": {},
+                "bailout": {{"Modulus": 4}}, "iterations": 200}}"#,
+            julia_c, initial_z
+        ))
+        .unwrap()
+    };
+
+    let mandelbrot: Mandelbrot =
+        serde_json::from_str(r#"{"bound": 4, "iterations": 200}"#).unwrap();
+    assert!(
+        formula("null", "null")
+            .build()
+            .unwrap()
+            .build_histogram(builder())
+            == mandelbrot.build().unwrap().build_histogram(builder())
+    );
+
+    let julia: Julia =
+        serde_json::from_str(r#"{"c": [-0.8, 0.156], "bound": 4, "iterations": 200}"#).unwrap();
+    assert!(
+        formula("[-0.8, 0.156]", "null")
+            .build()
+            .unwrap()
+            .build_histogram(builder())
+            == julia.build().unwrap().build_histogram(builder())
+    );
+
+    // The starting value of a Julia set is computed from the point
+    let histogram = formula("[-0.8, 0.156]", r#""z + 10""#)
+        .build()
+        .unwrap()
+        .build_histogram(builder());
+    for x in 0..60 {
+        for y in 0..60 {
+            assert_eq!(histogram.get_cell(x, y).0, 0.);
+        }
+    }
+}
+
+#[test]
+fn test_newton_basins() {
+    use super::histogram::HistogramBuilder;
+    use super::newton::{Newton, ROOT_CHANNEL};
+    use super::HistogramGeneration;
+    use crate::rendering::basin_rendering::BasinRendererConf;
+    use crate::rendering::HistogramRendering;
+
+    // z^3 - 1, whose roots are the cube roots of unity
+    let conf = r#"{"coefficients": [[-1, 0], [0, 0], [0, 0], [1, 0]],
+                   "tolerance": 1e-9, "iterations": 100}"#;
+    let renderer = || -> BasinRendererConf {
+        serde_json::from_str(
+            r#"{"root_colors": [[255, 0, 0], [0, 255, 0], [0, 0, 255]], "gamma": 0,
+                "interior_color": [0, 0, 0]}"#,
+        )
+        .unwrap()
+    };
+    // Number of iterations, root index and color at the point
+    let basin = |(x, y): (f64, f64)| -> (f64, f64, (u8, u8, u8)) {
+        let newton: Newton = serde_json::from_str(conf).unwrap();
+        let histogram = newton.build_histogram(HistogramBuilder::new(1, 1, 1, (x, y), 0., 0.));
+        let (freq, _) = histogram.get_cell(0, 0);
+        let root = histogram.get_channel_cell(histogram.channel_index(ROOT_CHANNEL).unwrap(), 0, 0);
+        let pix = &renderer().build().unwrap().render_image(histogram).pixels[0];
+        (freq, root, (pix.r, pix.g, pix.b))
+    };
+
+    let (cos, sin) = (-0.5, 3f64.sqrt() / 2.);
+    let near_roots: Vec<_> = [(1.1, 0.), (1.1 * cos, 1.1 * sin), (1.1 * cos, -1.1 * sin)]
+        .iter()
+        .map(|&point| basin(point))
+        .collect();
+    let mut roots: Vec<f64> = near_roots.iter().map(|&(_, root, _)| root).collect();
+    roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(roots, vec![1., 2., 3.]);
+    for &(_, root, color) in near_roots.iter() {
+        let expected = [(255, 0, 0), (0, 255, 0), (0, 0, 255)][root as usize - 1];
+        assert_eq!(color, expected);
+    }
+
+    // The root itself converges at once, and the farther points of its basin more slowly
+    let (freq_near, root_near, _) = near_roots[0];
+    assert_eq!(basin((1., 0.)), (0., root_near, near_roots[0].2));
+    let (freq_far, root_far, _) = basin((5., 0.));
+    assert_eq!(root_far, root_near);
+    assert!(freq_far > freq_near && freq_near > 0.);
+
+    assert!(serde_json::from_str::<Newton>(
+        r#"{"coefficients": [[-1, 0], [1, 0], [0, 0]], "tolerance": 1e-9, "iterations": 100}"#
+    )
+    .is_err());
+    let mut no_colors = renderer();
+    no_colors.root_colors.clear();
+    assert!(no_colors.build().is_err());
+}
+
+#[test]
+fn test_buddhabrot_accumulation() {
+    use super::buddhabrot::Buddhabrot;
+    use super::histogram::{Histogram, HistogramBuilder};
+    use super::HistogramGeneration;
+
+    // The values of c are sampled in a tiny square at the given point
+    let buddhabrot = |(x, y): (f64, f64), anti: bool| -> Histogram {
+        let buddhabrot: Buddhabrot = serde_json::from_str(&format!(
+            r#"{{"bound": 4, "iterations": 20, "anti": {}, "number_samples": 10,
+                "sample_area": [[{}, {}], [{}, {}]], "seed": "buddhabrot"}}"#,
+            anti,
+            x,
+            y,
+            x + 1e-6,
+            y + 1e-6
+        ))
+        .unwrap();
+        buddhabrot.build_histogram(HistogramBuilder::new(8, 8, 1, (0., 0.), 8., 8.))
+    };
+    let total = |histogram: &Histogram| -> f64 {
+        (0..8)
+            .flat_map(|x| (0..8).map(move |y| (x, y)))
+            .map(|(x, y)| histogram.get_cell(x, y).0)
+            .sum()
+    };
+
+    // The orbit of 1 is 1, 2, 5: its two points before the escape are plotted
+    let escaping = buddhabrot((1., 0.), false);
+    assert_eq!(escaping.get_cell(5, 4).0, 10.);
+    assert_eq!(escaping.get_cell(6, 4).0, 10.);
+    assert_eq!(total(&escaping), 20.);
+    assert_eq!(total(&buddhabrot((1., 0.), true)), 0.);
+
+    // -1.3 lies in a period-4 bulb, out of the cardioid and of the period-2 bulb: its orbit is
+    // iterated, but never escapes
+    assert_eq!(total(&buddhabrot((-1.3, 0.), false)), 0.);
+    assert_eq!(total(&buddhabrot((-1.3, 0.), true)), 200.);
+}
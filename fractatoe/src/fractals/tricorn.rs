@@ -0,0 +1,64 @@
+use num::complex::Complex;
+use serde_derive::{Deserialize, Serialize};
+
+use super::escape_time::{escape_time_histogram, iterate_until_escape};
+use super::histogram::{Histogram, HistogramBuilder};
+use super::HistogramGeneration;
+
+/// Tricorn (or Mandelbar): z -> conj(z)^2 + c, iterated from 0 for every c of the plane
+#[derive(Serialize, Deserialize)]
+pub struct Tricorn {
+    bound: f64,
+    iterations: usize,
+    #[serde(default)]
+    smooth: bool,
+}
+
+/// z -> conj(z)^2 + c for a fixed c, iterated from every z of the plane
+#[derive(Serialize, Deserialize)]
+pub struct TricornJulia {
+    c: (f64, f64),
+    bound: f64,
+    iterations: usize,
+    #[serde(default)]
+    smooth: bool,
+}
+
+fn tricorn_step(z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+    let z_conj = z.conj();
+    z_conj * z_conj + c
+}
+
+impl HistogramGeneration for Tricorn {
+    fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
+        let smooth_degree = if self.smooth { Some(2.) } else { None };
+        escape_time_histogram(&builder, |(x_float, y_float)| {
+            let c = Complex::new(x_float, y_float);
+            iterate_until_escape(
+                Complex::new(0., 0.),
+                0,
+                |z| tricorn_step(z, c),
+                self.bound,
+                self.iterations,
+                smooth_degree,
+            )
+        })
+    }
+}
+
+impl HistogramGeneration for TricornJulia {
+    fn build_histogram(self, builder: HistogramBuilder) -> Histogram {
+        let smooth_degree = if self.smooth { Some(2.) } else { None };
+        let c = Complex::new(self.c.0, self.c.1);
+        escape_time_histogram(&builder, |(x_float, y_float)| {
+            iterate_until_escape(
+                Complex::new(x_float, y_float),
+                0,
+                |z| tricorn_step(z, c),
+                self.bound,
+                self.iterations,
+                smooth_degree,
+            )
+        })
+    }
+}
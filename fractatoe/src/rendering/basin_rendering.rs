@@ -0,0 +1,134 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::{HistogramRendering, RgbColor};
+use crate::fractals::escape_time::INTERIOR_CHANNEL;
+use crate::fractals::histogram::Histogram;
+use crate::fractals::newton::ROOT_CHANNEL;
+
+use crate::image::{Image, Pix};
+
+#[derive(Serialize, Deserialize)]
+pub struct BasinRendererConf {
+    /// Color of the basin of each root, in the order of the root channel
+    pub root_colors: Vec<RgbColor>,
+    /// Exponent of the shading by convergence speed, 0 to disable it
+    pub gamma: f64,
+    /// Color of the points which converged elsewhere than to a root (e.g. Nova fractals)
+    #[serde(default = "default_no_root_color")]
+    pub no_root_color: RgbColor,
+    /// Color of the points which did not converge
+    pub interior_color: RgbColor,
+}
+
+fn default_no_root_color() -> RgbColor {
+    (255, 255, 255)
+}
+
+impl BasinRendererConf {
+    pub fn build(self) -> Result<BasinRenderer, String> {
+        if self.root_colors.is_empty() {
+            return Err("the basin renderer needs at least one root color".to_string());
+        }
+        Ok(BasinRenderer {
+            root_colors: self.root_colors,
+            gamma: self.gamma,
+            no_root_color: self.no_root_color,
+            interior_color: self.interior_color,
+        })
+    }
+}
+
+/// Colors every point by the root it converged to, darker as the convergence gets slower. The
+/// basins are anti-aliased by averaging the colors of the virtual pixels, since averaging root
+/// indices would be meaningless.
+pub struct BasinRenderer {
+    root_colors: Vec<RgbColor>,
+    gamma: f64,
+    no_root_color: RgbColor,
+    interior_color: RgbColor,
+}
+
+impl BasinRenderer {
+    /// Indices of the root and interior channels, given by the Newton fractals
+    fn channels(histogram: &Histogram) -> Result<(usize, usize), String> {
+        Ok((
+            histogram
+                .channel_index(ROOT_CHANNEL)
+                .ok_or("the histogram has no root channel")?,
+            histogram
+                .channel_index(INTERIOR_CHANNEL)
+                .ok_or("the histogram has no interior channel")?,
+        ))
+    }
+
+    fn color_of_cell(
+        &self,
+        histogram: &Histogram,
+        (root_channel, interior_channel): (usize, usize),
+        x: usize,
+        y: usize,
+        max_freq: f64,
+    ) -> (f64, f64, f64) {
+        if histogram.get_channel_cell(interior_channel, x, y) > 0. {
+            let (r, g, b) = self.interior_color;
+            return (r as f64, g as f64, b as f64);
+        }
+
+        let root = histogram.get_channel_cell(root_channel, x, y) as usize;
+        let (r, g, b) = match root {
+            0 => self.no_root_color,
+            _ => self.root_colors[(root - 1) % self.root_colors.len()],
+        };
+        let (freq, _) = histogram.get_cell(x, y);
+        let shade = (1. - freq / max_freq).max(0.).powf(self.gamma);
+        (r as f64 * shade, g as f64 * shade, b as f64 * shade)
+    }
+}
+
+impl HistogramRendering for BasinRenderer {
+    fn check_histogram(&self, histogram: &Histogram) -> Result<(), String> {
+        Self::channels(histogram).map(|_| ())
+    }
+
+    fn render_image(self, histogram: Histogram) -> Image {
+        let channels = Self::channels(&histogram).unwrap_or_else(|error| panic!("{}", error));
+        let resolution = histogram.resolution;
+
+        let mut max_freq: f64 = 0.;
+        for x in 0..(histogram.width * resolution) {
+            for y in 0..(histogram.height * resolution) {
+                max_freq = max_freq.max(histogram.get_cell(x, y).0);
+            }
+        }
+        // Avoids dividing by 0 when every point converges immediately
+        let max_freq = max_freq + 1.;
+
+        let mut image = Image::new(histogram.width, histogram.height);
+        let resolution_sq = (resolution * resolution) as f64;
+
+        for x in 0..histogram.width {
+            for y in 0..histogram.height {
+                let (mut r, mut g, mut b) = (0., 0., 0.);
+                for i in (x * resolution)..((x + 1) * resolution) {
+                    for j in (y * resolution)..((y + 1) * resolution) {
+                        let (r_cell, g_cell, b_cell) =
+                            self.color_of_cell(&histogram, channels, i, j, max_freq);
+                        r += r_cell;
+                        g += g_cell;
+                        b += b_cell;
+                    }
+                }
+
+                let pix = Pix {
+                    r: (r / resolution_sq) as u8,
+                    g: (g / resolution_sq) as u8,
+                    b: (b / resolution_sq) as u8,
+                    alpha: 0xff,
+                };
+                image.set_pixel(x, y, pix);
+            }
+        }
+
+        image
+    }
+}
@@ -0,0 +1,110 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::HistogramRendering;
+use crate::fractals::histogram::{FrequencyAggregationType, Histogram};
+
+use crate::image::{Image, Pix};
+
+/// Color added to the image with respect to the value of a histogram channel
+#[derive(Serialize, Deserialize)]
+pub struct ChannelColor {
+    pub channel: String,
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub gamma: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChannelRendererConf {
+    pub frequency_agreg_type: FrequencyAggregationType,
+    pub channel_colors: Vec<ChannelColor>,
+}
+
+impl ChannelRendererConf {
+    pub fn build(self) -> ChannelRenderer {
+        ChannelRenderer {
+            frequency_agreg_type: self.frequency_agreg_type,
+            channel_colors: self.channel_colors,
+        }
+    }
+}
+
+/// Sum of the colors of several channels, each one normalized by its maximal value (e.g. a
+/// Nebulabrot from its red, green and blue channels)
+pub struct ChannelRenderer {
+    frequency_agreg_type: FrequencyAggregationType,
+    channel_colors: Vec<ChannelColor>,
+}
+
+impl ChannelRenderer {
+    /// Indices of the channels of the colors
+    fn channels(&self, histogram: &Histogram) -> Result<Vec<usize>, String> {
+        self.channel_colors
+            .iter()
+            .map(|channel_color| {
+                histogram
+                    .channel_index(&channel_color.channel)
+                    .ok_or_else(|| format!("no channel {} in the histogram", channel_color.channel))
+            })
+            .collect()
+    }
+}
+
+impl HistogramRendering for ChannelRenderer {
+    fn check_histogram(&self, histogram: &Histogram) -> Result<(), String> {
+        self.channels(histogram).map(|_| ())
+    }
+
+    fn render_image(self, mut histogram: Histogram) -> Image {
+        histogram.reduce_resolution(FrequencyAggregationType::Linear);
+        let mut image = Image::new(histogram.width, histogram.height);
+
+        let channels: Vec<(usize, f64)> = self
+            .channels(&histogram)
+            .unwrap_or_else(|error| panic!("{}", error))
+            .into_iter()
+            .map(|channel| {
+                let mut max_value: f64 = 0.;
+                for x in 0..histogram.width {
+                    for y in 0..histogram.height {
+                        max_value = max_value.max(histogram.get_channel_cell(channel, x, y));
+                    }
+                }
+                (channel, max_value)
+            })
+            .collect();
+
+        for x in 0..histogram.width {
+            for y in 0..histogram.height {
+                let (mut r, mut g, mut b): (f64, f64, f64) = (0., 0., 0.);
+
+                for (channel_color, &(channel, max_value)) in
+                    self.channel_colors.iter().zip(channels.iter())
+                {
+                    let value = histogram.get_channel_cell(channel, x, y);
+                    let intensity = match self.frequency_agreg_type {
+                        FrequencyAggregationType::Linear => value / max_value,
+                        FrequencyAggregationType::Logarithmic => {
+                            (1. + value).ln() / (1. + max_value).ln()
+                        }
+                    };
+                    let intensity = intensity.max(0.).powf(channel_color.gamma);
+                    r += intensity * channel_color.r;
+                    g += intensity * channel_color.g;
+                    b += intensity * channel_color.b;
+                }
+
+                let pix = Pix {
+                    r: r.clamp(0., 255.) as u8,
+                    g: g.clamp(0., 255.) as u8,
+                    b: b.clamp(0., 255.) as u8,
+                    alpha: 0xff,
+                };
+
+                image.set_pixel(x, y, pix)
+            }
+        }
+        image
+    }
+}
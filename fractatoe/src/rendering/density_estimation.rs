@@ -0,0 +1,208 @@
+use std::ops::Range;
+
+use rayon::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::fractals::histogram::Histogram;
+
+/// Subdivisions of a virtual pixel between two radii of the cached kernels
+const RADIUS_STEPS: f64 = 4.;
+
+/// Density estimation of flam3: the points of every virtual pixel are spread by a kernel whose
+/// radius decreases with their number, blurring the noisy sparse parts of the histogram while
+/// keeping the dense ones sharp.
+#[derive(Serialize, Deserialize)]
+pub struct DensityEstimationConf {
+    /// Radius of the kernel of the virtual pixels with a single point, in pixels of the image
+    pub max_radius: f64,
+    /// Radius under which the points are not spread, in pixels of the image
+    pub min_radius: f64,
+    /// Exponent of the density by which the maximal radius is divided
+    pub curve: f64,
+}
+
+/// Weights of the virtual pixels covered by a kernel, relative to its center
+type Kernel = Vec<(isize, isize, f64)>;
+
+/// Epanechnikov kernel of the given radius, in virtual pixels, normalized to 1
+fn kernel(radius: f64) -> Kernel {
+    let extent = radius.floor() as isize;
+    // Row by row, to spread the points in order in the memory
+    let mut kernel: Kernel = (-extent..=extent)
+        .flat_map(|j| (-extent..=extent).map(move |i| (i, j)))
+        .map(|(i, j)| {
+            let distance_sq = ((i * i + j * j) as f64) / (radius * radius);
+            (i, j, (1. - distance_sq).max(0.))
+        })
+        .filter(|&(_, _, weight)| weight > 0.)
+        .collect();
+    let total: f64 = kernel.iter().map(|&(_, _, weight)| weight).sum();
+    for (_, _, weight) in kernel.iter_mut() {
+        *weight /= total;
+    }
+    kernel
+}
+
+/// Radius, in virtual pixels, of the cached kernel of the given step
+fn kernel_radius(step: usize) -> f64 {
+    step as f64 / RADIUS_STEPS
+}
+
+/// Frequencies, sums of the colors weighted by the frequencies, and channels spread over a band
+/// of rows of virtual pixels
+struct Spread {
+    rows: Range<usize>,
+    cells: Vec<(f64, f64)>,
+    channels: Vec<Vec<f64>>,
+}
+
+impl Spread {
+    fn new(width: usize, rows: Range<usize>, number_channels: usize) -> Self {
+        let size = width * rows.len();
+        Spread {
+            rows,
+            cells: vec![(0., 0.); size],
+            channels: vec![vec![0.; size]; number_channels],
+        }
+    }
+}
+
+impl DensityEstimationConf {
+    /// Step of the cached kernel of the virtual pixels with the given frequency, None if their
+    /// points are not spread
+    fn kernel_step(&self, freq: f64, resolution: f64) -> Option<usize> {
+        let radius = self.max_radius * resolution / freq.powf(self.curve);
+        if radius < (self.min_radius * resolution).max(1.) {
+            None
+        } else {
+            Some((radius * RADIUS_STEPS).round() as usize)
+        }
+    }
+
+    /// Spread the virtual pixels of the given rows, into the band of the rows they reach, at most
+    /// `margin` rows away
+    fn spread_rows(&self, histogram: &Histogram, rows: Range<usize>, margin: usize) -> Spread {
+        let resolution = histogram.resolution as f64;
+        let (width, height) = (
+            histogram.width * histogram.resolution,
+            histogram.height * histogram.resolution,
+        );
+        let number_channels = histogram.number_channels();
+
+        let mut kernels: Vec<Option<Kernel>> = vec![];
+        let no_spread: Kernel = vec![(0, 0, 1.)];
+        let band = rows.start.saturating_sub(margin)..(rows.end + margin).min(height);
+        let mut spread = Spread::new(width, band, number_channels);
+        let mut channels: Vec<f64> = Vec::with_capacity(number_channels);
+        for y in rows {
+            for x in 0..width {
+                let (freq, color) = histogram.get_cell(x, y);
+                if freq <= 0. {
+                    continue;
+                }
+                channels.clear();
+                channels.extend(
+                    (0..number_channels).map(|channel| histogram.get_channel_cell(channel, x, y)),
+                );
+
+                let kernel = match self.kernel_step(freq, resolution) {
+                    None => &no_spread,
+                    Some(step) => {
+                        if kernels.len() <= step {
+                            kernels.resize(step + 1, None);
+                        }
+                        kernels[step].get_or_insert_with(|| kernel(kernel_radius(step)))
+                    }
+                };
+                for &(i, j, weight) in kernel.iter() {
+                    let (x_spread, y_spread) = (x as isize + i, y as isize + j);
+                    if (0..width as isize).contains(&x_spread)
+                        && (0..height as isize).contains(&y_spread)
+                    {
+                        let index =
+                            x_spread as usize + (y_spread as usize - spread.rows.start) * width;
+                        spread.cells[index].0 += weight * freq;
+                        spread.cells[index].1 += weight * freq * color;
+                        for (spread_channel, value) in
+                            spread.channels.iter_mut().zip(channels.iter())
+                        {
+                            spread_channel[index] += weight * value;
+                        }
+                    }
+                }
+            }
+        }
+        spread
+    }
+
+    /// Filter the virtual pixels of the histogram, before its resolution is reduced. The channels
+    /// are spread like the frequencies, as sums over the points such as the color channels.
+    ///
+    /// The rows are split among the threads of the current rayon thread pool, each one spreading
+    /// its rows into the band of rows reached by its kernels. The bands are added in order, so
+    /// that the result only depends on the number of threads.
+    pub fn filter(&self, histogram: &mut Histogram) {
+        let resolution = histogram.resolution as f64;
+        let (width, height) = (
+            histogram.width * histogram.resolution,
+            histogram.height * histogram.resolution,
+        );
+        let number_channels = histogram.number_channels();
+
+        // Number of rows reached by the largest kernel on either side of its center
+        let mut margin = 0;
+        for x in 0..width {
+            for y in 0..height {
+                let (freq, _) = histogram.get_cell(x, y);
+                if freq <= 0. {
+                    continue;
+                }
+                if let Some(step) = self.kernel_step(freq, resolution) {
+                    margin = margin.max(kernel_radius(step).floor() as usize);
+                }
+            }
+        }
+
+        let number_workers = rayon::current_num_threads().clamp(1, height.max(1));
+        let rows_per_worker = height.div_ceil(number_workers);
+        let spreads: Vec<Spread> = (0..number_workers)
+            .into_par_iter()
+            .map(|worker| {
+                let first_row = (worker * rows_per_worker).min(height);
+                let last_row = (first_row + rows_per_worker).min(height);
+                self.spread_rows(histogram, first_row..last_row, margin)
+            })
+            .collect();
+
+        // The sums of the colors are kept in the cells until all the bands are added
+        for x in 0..width {
+            for y in 0..height {
+                histogram.set_cell(x, y, (0., 0.));
+                for channel in 0..number_channels {
+                    histogram.set_channel_cell(channel, x, y, 0.);
+                }
+            }
+        }
+        for spread in spreads.iter() {
+            for (row, y) in spread.rows.clone().enumerate() {
+                for x in 0..width {
+                    let index = x + row * width;
+                    let (freq, color_sum) = histogram.get_cell(x, y);
+                    let (spread_freq, spread_color_sum) = spread.cells[index];
+                    histogram.set_cell(x, y, (freq + spread_freq, color_sum + spread_color_sum));
+                    for (channel, spread_channel) in spread.channels.iter().enumerate() {
+                        let value = histogram.get_channel_cell(channel, x, y);
+                        histogram.set_channel_cell(channel, x, y, value + spread_channel[index]);
+                    }
+                }
+            }
+        }
+        for x in 0..width {
+            for y in 0..height {
+                let (freq, color_sum) = histogram.get_cell(x, y);
+                let color = if freq > 0. { color_sum / freq } else { 0. };
+                histogram.set_cell(x, y, (freq, color));
+            }
+        }
+    }
+}
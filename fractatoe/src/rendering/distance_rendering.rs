@@ -0,0 +1,123 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::{HistogramRendering, RgbColor};
+use crate::fractals::escape_time::{DISTANCE_CHANNEL, INTERIOR_CHANNEL};
+use crate::fractals::histogram::Histogram;
+
+use crate::image::{Image, Pix};
+
+#[derive(Serialize, Deserialize)]
+pub struct DistanceRendererConf {
+    /// Color of the boundary of the set
+    pub boundary_color: RgbColor,
+    /// Color of the points far from the boundary
+    pub background_color: RgbColor,
+    /// Distance to the boundary, in pixels of the image, from which the background color is used
+    pub thickness: f64,
+    pub gamma: f64,
+    /// Color of the points which never escaped, the boundary color if absent
+    #[serde(default)]
+    pub interior_color: Option<RgbColor>,
+}
+
+impl DistanceRendererConf {
+    pub fn build(self) -> DistanceRenderer {
+        DistanceRenderer {
+            boundary_color: self.boundary_color,
+            background_color: self.background_color,
+            thickness: self.thickness,
+            gamma: self.gamma,
+            interior_color: self.interior_color.unwrap_or(self.boundary_color),
+        }
+    }
+}
+
+/// Shades every point by its exterior distance estimate, from the boundary color on the boundary
+/// to the background color at `thickness` pixels of it. Thin filaments, which are missed by the
+/// iteration counts, are drawn with a constant width. The virtual pixels are rendered before being
+/// averaged.
+pub struct DistanceRenderer {
+    boundary_color: RgbColor,
+    background_color: RgbColor,
+    thickness: f64,
+    gamma: f64,
+    interior_color: RgbColor,
+}
+
+impl DistanceRenderer {
+    /// Indices of the distance and interior channels, given by the distance estimation
+    fn channels(histogram: &Histogram) -> Result<(usize, usize), String> {
+        Ok((
+            histogram
+                .channel_index(DISTANCE_CHANNEL)
+                .ok_or("the histogram has no distance channel")?,
+            histogram
+                .channel_index(INTERIOR_CHANNEL)
+                .ok_or("the histogram has no interior channel")?,
+        ))
+    }
+
+    fn color_of_cell(
+        &self,
+        histogram: &Histogram,
+        (distance_channel, interior_channel): (usize, usize),
+        x: usize,
+        y: usize,
+    ) -> (f64, f64, f64) {
+        if histogram.get_channel_cell(interior_channel, x, y) > 0. {
+            let (r, g, b) = self.interior_color;
+            return (r as f64, g as f64, b as f64);
+        }
+
+        let distance =
+            histogram.get_channel_cell(distance_channel, x, y) / histogram.resolution as f64;
+        let intensity = (distance / self.thickness).clamp(0., 1.).powf(self.gamma);
+        let blend = |boundary: u8, background: u8| {
+            boundary as f64 * (1. - intensity) + background as f64 * intensity
+        };
+        (
+            blend(self.boundary_color.0, self.background_color.0),
+            blend(self.boundary_color.1, self.background_color.1),
+            blend(self.boundary_color.2, self.background_color.2),
+        )
+    }
+}
+
+impl HistogramRendering for DistanceRenderer {
+    fn check_histogram(&self, histogram: &Histogram) -> Result<(), String> {
+        Self::channels(histogram).map(|_| ())
+    }
+
+    fn render_image(self, histogram: Histogram) -> Image {
+        let channels = Self::channels(&histogram).unwrap_or_else(|error| panic!("{}", error));
+        let resolution = histogram.resolution;
+
+        let mut image = Image::new(histogram.width, histogram.height);
+        let resolution_sq = (resolution * resolution) as f64;
+
+        for x in 0..histogram.width {
+            for y in 0..histogram.height {
+                let (mut r, mut g, mut b) = (0., 0., 0.);
+                for i in (x * resolution)..((x + 1) * resolution) {
+                    for j in (y * resolution)..((y + 1) * resolution) {
+                        let (r_cell, g_cell, b_cell) =
+                            self.color_of_cell(&histogram, channels, i, j);
+                        r += r_cell;
+                        g += g_cell;
+                        b += b_cell;
+                    }
+                }
+
+                let pix = Pix {
+                    r: (r / resolution_sq) as u8,
+                    g: (g / resolution_sq) as u8,
+                    b: (b / resolution_sq) as u8,
+                    alpha: 0xff,
+                };
+                image.set_pixel(x, y, pix);
+            }
+        }
+
+        image
+    }
+}
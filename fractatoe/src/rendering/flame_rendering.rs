@@ -1,5 +1,6 @@
 use serde_derive::{Deserialize, Serialize};
 
+use super::density_estimation::DensityEstimationConf;
 use super::HistogramRendering;
 use crate::fractals::histogram::{FrequencyAggregationType, Histogram};
 
@@ -12,6 +13,9 @@ pub struct FlameRendererConf {
     pub r: u8,
     pub g: u8,
     pub b: u8,
+    /// Blur of the sparse virtual pixels, before the resolution is reduced
+    #[serde(default)]
+    pub density_estimation: Option<DensityEstimationConf>,
 }
 
 impl FlameRendererConf {
@@ -22,6 +26,7 @@ impl FlameRendererConf {
             r: self.r,
             g: self.g,
             b: self.b,
+            density_estimation: self.density_estimation,
         }
     }
 }
@@ -31,11 +36,15 @@ pub struct FlameRenderer {
     g: u8,
     b: u8,
     gamma: f64,
+    density_estimation: Option<DensityEstimationConf>,
 }
 
 impl HistogramRendering for FlameRenderer {
     fn render_image(self, mut histogram: Histogram) -> Image {
-        histogram.reduce_resolution(self.frequency_agreg_type);
+        if let Some(density_estimation) = &self.density_estimation {
+            density_estimation.filter(&mut histogram);
+        }
+        histogram.reduce_density_resolution(self.frequency_agreg_type);
 
         let mut image = Image::new(histogram.width, histogram.height);
 
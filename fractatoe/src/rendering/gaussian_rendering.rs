@@ -1,6 +1,6 @@
 use serde_derive::{Deserialize, Serialize};
 
-use super::HistogramRendering;
+use super::{blend_interior_color, HistogramRendering, RgbColor};
 use crate::fractals::histogram::{FrequencyAggregationType, Histogram};
 
 use crate::image::{Image, Pix};
@@ -44,6 +44,9 @@ pub struct GaussianRendererConf {
     pub gaussian_colors: Vec<GaussianColor>,
 
     pub gamma: f64,
+    /// Color of the points which never escaped
+    #[serde(default)]
+    pub interior_color: Option<RgbColor>,
 }
 
 impl GaussianRendererConf {
@@ -52,6 +55,7 @@ impl GaussianRendererConf {
             frequency_agreg_type: self.frequency_agreg_type,
             gaussian_colors: self.gaussian_colors,
             gamma: self.gamma,
+            interior_color: self.interior_color,
         }
     }
 }
@@ -62,6 +66,7 @@ pub struct GaussianRenderer {
     gaussian_colors: Vec<GaussianColor>,
 
     pub gamma: f64,
+    interior_color: Option<RgbColor>,
 }
 
 impl HistogramRendering for GaussianRenderer {
@@ -91,6 +96,7 @@ impl HistogramRendering for GaussianRenderer {
                     alpha: 0xff,
                 };
 
+                let pix = blend_interior_color(pix, self.interior_color, &histogram, x, y);
                 image.set_pixel(x, y, pix)
             }
         }
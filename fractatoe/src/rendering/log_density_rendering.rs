@@ -0,0 +1,171 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::density_estimation::DensityEstimationConf;
+use super::{HistogramRendering, RgbColor};
+use crate::fractals::histogram::Histogram;
+
+use crate::image::{Image, Pix};
+
+#[derive(Serialize, Deserialize)]
+pub struct LogDensityRendererConf {
+    /// Color of the points of color 1, the other ones being darker, if the histogram has no red,
+    /// green and blue channels
+    pub color: RgbColor,
+    /// Color of the empty pixels, blended with the sparse ones
+    #[serde(default)]
+    pub background_color: RgbColor,
+    /// Multiplier of the log-density, 1 mapping the densest pixel to the full intensity
+    pub brightness: f64,
+    pub gamma: f64,
+    /// Between 0 and 1: proportion of the gamma correction applied to the intensity of the pixels
+    /// rather than to each of their channels. The former keeps the colors saturated.
+    pub vibrancy: f64,
+    /// Desaturation of the pixels which would be brighter than white, which are otherwise clipped
+    #[serde(default)]
+    pub highlight_power: Option<f64>,
+    /// Blur of the sparse virtual pixels
+    #[serde(default)]
+    pub density_estimation: Option<DensityEstimationConf>,
+}
+
+impl LogDensityRendererConf {
+    pub fn build(self) -> LogDensityRenderer {
+        let channel = |value: u8| value as f64 / 255.;
+        LogDensityRenderer {
+            color: [
+                channel(self.color.0),
+                channel(self.color.1),
+                channel(self.color.2),
+            ],
+            background_color: [
+                channel(self.background_color.0),
+                channel(self.background_color.1),
+                channel(self.background_color.2),
+            ],
+            brightness: self.brightness,
+            gamma: self.gamma,
+            vibrancy: self.vibrancy,
+            highlight_power: self.highlight_power,
+            density_estimation: self.density_estimation,
+        }
+    }
+}
+
+/// Log-density display of flam3: the intensity of a pixel is the logarithm of its number of
+/// points, relative to the densest pixel, so that the sparse parts of the flame stay visible. The
+/// virtual pixels are summed before the tone mapping.
+pub struct LogDensityRenderer {
+    color: [f64; 3],
+    background_color: [f64; 3],
+    brightness: f64,
+    gamma: f64,
+    vibrancy: f64,
+    highlight_power: Option<f64>,
+    density_estimation: Option<DensityEstimationConf>,
+}
+
+impl LogDensityRenderer {
+    /// Channels of a pixel, between 0 and 1, from its density and the sum of the colors of its
+    /// points
+    pub(super) fn tone_map(
+        &self,
+        density: f64,
+        color_sum: [f64; 3],
+        log_max_density: f64,
+    ) -> [f64; 3] {
+        if density <= 0. {
+            return self.background_color;
+        }
+        let log_alpha = self.brightness * density.ln_1p() / log_max_density;
+        let alpha = log_alpha.powf(1. / self.gamma);
+
+        let mut log_color = color_sum;
+        for channel in log_color.iter_mut() {
+            *channel *= log_alpha / density;
+        }
+
+        let mut rgb = self.scale_color(log_color, self.vibrancy * alpha / log_alpha);
+        let background = 1. - alpha.min(1.);
+        for (i, channel) in rgb.iter_mut().enumerate() {
+            *channel += (1. - self.vibrancy) * log_color[i].powf(1. / self.gamma)
+                + background * self.background_color[i];
+        }
+        rgb
+    }
+
+    /// Multiply the color by `scale`. With a highlight power, the colors which would overflow are
+    /// scaled to fit instead, and desaturated toward white the more they overflow.
+    fn scale_color(&self, color: [f64; 3], scale: f64) -> [f64; 3] {
+        let max_channel = color.iter().cloned().fold(0., f64::max);
+        let mut scaled = color;
+        match self.highlight_power {
+            Some(power) if scale * max_channel > 1. => {
+                let fitting_scale = 1. / max_channel;
+                let saturation = (fitting_scale / scale).powf(power);
+                for channel in scaled.iter_mut() {
+                    *channel = 1. - (1. - *channel * fitting_scale) * saturation;
+                }
+            }
+            _ => {
+                for channel in scaled.iter_mut() {
+                    *channel *= scale;
+                }
+            }
+        }
+        scaled
+    }
+}
+
+impl HistogramRendering for LogDensityRenderer {
+    fn render_image(self, mut histogram: Histogram) -> Image {
+        if let Some(density_estimation) = &self.density_estimation {
+            density_estimation.filter(&mut histogram);
+        }
+        let resolution = histogram.resolution;
+        let color_channels = histogram.color_channels();
+
+        // Number of points of every pixel, and sum of their colors
+        let mut pixels = vec![(0., [0.; 3]); histogram.width * histogram.height];
+        for x in 0..(histogram.width * resolution) {
+            for y in 0..(histogram.height * resolution) {
+                let (freq, color) = histogram.get_cell(x, y);
+                let pixel = &mut pixels[x / resolution + (y / resolution) * histogram.width];
+                pixel.0 += freq;
+                let color_sum = match color_channels {
+                    Some(channels) => histogram.get_color_sum(channels, x, y),
+                    None => {
+                        let mut color_sum = self.color;
+                        for component in color_sum.iter_mut() {
+                            *component *= freq * color;
+                        }
+                        color_sum
+                    }
+                };
+                for (sum, component) in pixel.1.iter_mut().zip(color_sum.iter()) {
+                    *sum += component;
+                }
+            }
+        }
+        let max_density = pixels.iter().map(|&(freq, _)| freq).fold(0., f64::max);
+        let log_max_density = max_density.ln_1p();
+
+        let mut image = Image::new(histogram.width, histogram.height);
+        for x in 0..histogram.width {
+            for y in 0..histogram.height {
+                let (density, color_sum) = pixels[x + y * histogram.width];
+                let rgb = self.tone_map(density, color_sum, log_max_density);
+                let channel = |value: f64| (value.clamp(0., 1.) * 255.) as u8;
+
+                let pix = Pix {
+                    r: channel(rgb[0]),
+                    g: channel(rgb[1]),
+                    b: channel(rgb[2]),
+                    alpha: 0xff,
+                };
+                image.set_pixel(x, y, pix);
+            }
+        }
+
+        image
+    }
+}
@@ -1,6 +1,6 @@
 use serde_derive::{Deserialize, Serialize};
 
-use super::HistogramRendering;
+use super::{blend_interior_color, HistogramRendering, RgbColor};
 use crate::fractals::histogram::{FrequencyAggregationType, Histogram};
 
 use crate::image::{Image, Pix};
@@ -10,6 +10,9 @@ pub struct GreyscaleRendererConf {
     pub gamma: f64,
 
     pub frequency_agreg_type: FrequencyAggregationType,
+    /// Color of the points which never escaped
+    #[serde(default)]
+    pub interior_color: Option<RgbColor>,
 }
 
 impl GreyscaleRendererConf {
@@ -20,6 +23,7 @@ impl GreyscaleRendererConf {
             b: 255,
             gamma: self.gamma,
             frequency_agreg_type: self.frequency_agreg_type,
+            interior_color: self.interior_color,
         }
     }
 }
@@ -32,6 +36,9 @@ pub struct MandelbrotRendererConf {
     pub gamma: f64,
 
     pub frequency_agreg_type: FrequencyAggregationType,
+    /// Color of the points which never escaped
+    #[serde(default)]
+    pub interior_color: Option<RgbColor>,
 }
 
 impl MandelbrotRendererConf {
@@ -42,6 +49,7 @@ impl MandelbrotRendererConf {
             b: self.b,
             gamma: self.gamma,
             frequency_agreg_type: self.frequency_agreg_type,
+            interior_color: self.interior_color,
         }
     }
 }
@@ -52,6 +60,7 @@ pub struct MandelbrotRenderer {
     gamma: f64,
 
     frequency_agreg_type: FrequencyAggregationType,
+    interior_color: Option<RgbColor>,
 }
 
 impl HistogramRendering for MandelbrotRenderer {
@@ -68,6 +77,7 @@ impl HistogramRendering for MandelbrotRenderer {
                     b: ((self.b as f64) * (freq as f64).powf(self.gamma)) as u8,
                     alpha: 0xff,
                 };
+                let pix = blend_interior_color(pix, self.interior_color, &histogram, x, y);
                 image.set_pixel(x, y, pix);
             }
         }
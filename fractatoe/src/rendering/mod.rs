@@ -1,11 +1,53 @@
-use crate::image::Image;
+use crate::image::{Image, Pix};
 
+pub mod basin_rendering;
+pub mod channel_rendering;
+pub mod density_estimation;
+pub mod distance_rendering;
 pub mod flame_rendering;
 pub mod gaussian_rendering;
+pub mod log_density_rendering;
 pub mod mandelbrot_rendering;
 
+#[cfg(test)]
+mod test;
+
+use crate::fractals::escape_time::INTERIOR_CHANNEL;
 use crate::fractals::histogram::Histogram;
 
+pub type RgbColor = (u8, u8, u8);
+
 pub trait HistogramRendering {
+    /// Check that the histogram has the channels needed by the renderer, `render_image` panicking
+    /// otherwise
+    fn check_histogram(&self, _histogram: &Histogram) -> Result<(), String> {
+        Ok(())
+    }
     fn render_image(self, histogram: Histogram) -> Image;
 }
+
+/// Blend the pixel at (x, y) with the interior color, with respect to the proportion of interior
+/// points in this pixel. Does nothing if the histogram does not flag its interior points.
+fn blend_interior_color(
+    pix: Pix,
+    interior_color: Option<RgbColor>,
+    histogram: &Histogram,
+    x: usize,
+    y: usize,
+) -> Pix {
+    match (interior_color, histogram.channel_index(INTERIOR_CHANNEL)) {
+        (Some((r, g, b)), Some(channel)) => {
+            let proportion = histogram.get_channel_cell(channel, x, y);
+            let blend = |color: u8, interior: u8| {
+                (color as f64 * (1. - proportion) + interior as f64 * proportion) as u8
+            };
+            Pix {
+                r: blend(pix.r, r),
+                g: blend(pix.g, g),
+                b: blend(pix.b, b),
+                alpha: pix.alpha,
+            }
+        }
+        _ => pix,
+    }
+}
@@ -0,0 +1,226 @@
+use super::density_estimation::DensityEstimationConf;
+use super::log_density_rendering::{LogDensityRenderer, LogDensityRendererConf};
+use crate::fractals::histogram::Histogram;
+
+fn log_density_renderer(conf: &str) -> LogDensityRenderer {
+    serde_json::from_str::<LogDensityRendererConf>(conf)
+        .unwrap()
+        .build()
+}
+
+fn assert_close(rgb: [f64; 3], expected: [f64; 3]) {
+    for (channel, expected_channel) in rgb.iter().zip(expected.iter()) {
+        assert!((channel - expected_channel).abs() < 1e-12, "{:?}", rgb);
+    }
+}
+
+#[test]
+fn test_log_density_background() {
+    let renderer = log_density_renderer(
+        r#"{"color": [255, 255, 255], "background_color": [255, 0, 0],
+            "brightness": 1, "gamma": 2, "vibrancy": 1}"#,
+    );
+    let log_max_density = 100f64.ln_1p();
+
+    assert_close(
+        renderer.tone_map(0., [0.; 3], log_max_density),
+        [1., 0., 0.],
+    );
+
+    // The densest pixel is fully opaque: its mean color hides the background
+    let rgb = renderer.tone_map(100., [100., 50., 0.], log_max_density);
+    assert_close(rgb, [1., 0.5, 0.]);
+}
+
+#[test]
+fn test_log_density_vibrancy() {
+    let renderer = |vibrancy: f64| {
+        log_density_renderer(&format!(
+            r#"{{"color": [255, 255, 255], "brightness": 1, "gamma": 2, "vibrancy": {}}}"#,
+            vibrancy
+        ))
+    };
+    // Pixel of 10 points of mean color (1, 0.25, 0)
+    let log_max_density = 1000f64.ln_1p();
+    let log_alpha = 10f64.ln_1p() / log_max_density;
+    let color_sum = [10., 2.5, 0.];
+
+    // The gamma correction of the intensity keeps the hue
+    let alpha = log_alpha.sqrt();
+    let saturated = renderer(1.).tone_map(10., color_sum, log_max_density);
+    assert_close(saturated, [alpha, 0.25 * alpha, 0.]);
+
+    // The gamma correction of every channel brightens the weaker ones
+    let washed_out = renderer(0.).tone_map(10., color_sum, log_max_density);
+    assert_close(
+        washed_out,
+        [log_alpha.sqrt(), (0.25 * log_alpha).sqrt(), 0.],
+    );
+    assert!(washed_out[1] / washed_out[0] > saturated[1] / saturated[0]);
+}
+
+#[test]
+fn test_log_density_highlight_power() {
+    let renderer = |highlight_power: &str| {
+        log_density_renderer(&format!(
+            r#"{{"color": [255, 255, 255], "brightness": 4, "gamma": 1, "vibrancy": 1,
+                "highlight_power": {}}}"#,
+            highlight_power
+        ))
+    };
+    // The densest pixel, of mean color (1, 0.25, 0), is 4 times brighter than white
+    let log_max_density = 100f64.ln_1p();
+    let color_sum = [100., 25., 0.];
+
+    let clipped = renderer("null").tone_map(100., color_sum, log_max_density);
+    assert_close(clipped, [4., 1., 0.]);
+
+    let highlighted = renderer("1").tone_map(100., color_sum, log_max_density);
+    assert_close(highlighted, [1., 0.8125, 0.75]);
+    assert!(highlighted.iter().all(|&channel| channel <= 1.));
+    assert!(highlighted[2] > 0.);
+}
+
+fn density_estimation() -> DensityEstimationConf {
+    serde_json::from_str(r#"{"max_radius": 3, "min_radius": 1, "curve": 0.5}"#).unwrap()
+}
+
+/// 21x21 histogram with a single non-empty cell at its center, whose channel holds its frequency
+fn single_cell_histogram(freq: f64, color: f64) -> Histogram {
+    let mut histogram = Histogram::new(21, 21, 1);
+    histogram.set_cell(10, 10, (freq, color));
+    let mut channel = vec![0.; 21 * 21];
+    channel[10 + 21 * 10] = freq;
+    histogram.add_channel("points", channel);
+    histogram
+}
+
+#[test]
+fn test_density_estimation_spreads_sparse_cells() {
+    let mut histogram = single_cell_histogram(1., 0.3);
+    density_estimation().filter(&mut histogram);
+
+    let mut total = (0., 0.);
+    let mut spread_cells = 0;
+    for x in 0..21 {
+        for y in 0..21 {
+            let (freq, color) = histogram.get_cell(x, y);
+            total.0 += freq;
+            total.1 += histogram.get_channel_cell(0, x, y);
+            if freq > 0. {
+                spread_cells += 1;
+                assert!((color - 0.3).abs() < 1e-12);
+                // Within the radius of 3 pixels
+                assert!((x as f64 - 10.).hypot(y as f64 - 10.) < 3.);
+            }
+        }
+    }
+    assert!(spread_cells > 1);
+    assert!(histogram.get_cell(10, 10).0 < 1.);
+    assert!((total.0 - 1.).abs() < 1e-12 && (total.1 - 1.).abs() < 1e-12);
+
+    // The rows are spread by several threads, into bands of rows overlapping around the cell,
+    // with the same result
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap();
+    let mut parallel = single_cell_histogram(1., 0.3);
+    pool.install(|| density_estimation().filter(&mut parallel));
+    for x in 0..21 {
+        for y in 0..21 {
+            let ((freq, color), (parallel_freq, parallel_color)) =
+                (histogram.get_cell(x, y), parallel.get_cell(x, y));
+            assert!((freq - parallel_freq).abs() < 1e-12);
+            assert!((color - parallel_color).abs() < 1e-12);
+            let (value, parallel_value) = (
+                histogram.get_channel_cell(0, x, y),
+                parallel.get_channel_cell(0, x, y),
+            );
+            assert!((value - parallel_value).abs() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn test_density_estimation_keeps_dense_cells() {
+    // The radius 3 / sqrt(freq) is below the minimal radius from 9 points on
+    for &freq in [10., 100.].iter() {
+        let mut histogram = single_cell_histogram(freq, 0.3);
+        density_estimation().filter(&mut histogram);
+        for x in 0..21 {
+            for y in 0..21 {
+                let expected = if (x, y) == (10, 10) {
+                    (freq, 0.3)
+                } else {
+                    (0., 0.)
+                };
+                assert_eq!(histogram.get_cell(x, y), expected);
+            }
+        }
+        assert_eq!(histogram.get_channel_cell(0, 10, 10), freq);
+    }
+}
+
+#[test]
+fn test_basin_rendering_checks_channels() {
+    use super::basin_rendering::BasinRendererConf;
+    use super::HistogramRendering;
+    use crate::fractals::escape_time::INTERIOR_CHANNEL;
+    use crate::fractals::newton::ROOT_CHANNEL;
+
+    let renderer = serde_json::from_str::<BasinRendererConf>(
+        r#"{"root_colors": [[255, 0, 0]], "gamma": 1, "interior_color": [0, 0, 0]}"#,
+    )
+    .unwrap()
+    .build()
+    .unwrap();
+
+    let mut histogram = Histogram::new(2, 2, 1);
+    assert!(renderer.check_histogram(&histogram).is_err());
+    histogram.add_channel(ROOT_CHANNEL, vec![1.; 4]);
+    assert!(renderer.check_histogram(&histogram).is_err());
+    histogram.add_channel(INTERIOR_CHANNEL, vec![0.; 4]);
+    assert!(renderer.check_histogram(&histogram).is_ok());
+}
+
+#[test]
+fn test_distance_rendering_checks_channels() {
+    use super::distance_rendering::DistanceRendererConf;
+    use super::HistogramRendering;
+    use crate::fractals::escape_time::{DISTANCE_CHANNEL, INTERIOR_CHANNEL};
+
+    let renderer = serde_json::from_str::<DistanceRendererConf>(
+        r#"{"boundary_color": [0, 0, 0], "background_color": [255, 255, 255],
+            "thickness": 2, "gamma": 1}"#,
+    )
+    .unwrap()
+    .build();
+
+    let mut histogram = Histogram::new(2, 2, 1);
+    histogram.add_channel(INTERIOR_CHANNEL, vec![0.; 4]);
+    assert!(renderer.check_histogram(&histogram).is_err());
+    histogram.add_channel(DISTANCE_CHANNEL, vec![1.; 4]);
+    assert!(renderer.check_histogram(&histogram).is_ok());
+}
+
+#[test]
+fn test_channel_rendering_checks_channels() {
+    use super::channel_rendering::ChannelRendererConf;
+    use super::HistogramRendering;
+
+    let renderer = serde_json::from_str::<ChannelRendererConf>(
+        r#"{"frequency_agreg_type": "Linear",
+            "channel_colors": [{"channel": "red", "r": 255, "g": 0, "b": 0, "gamma": 1},
+                               {"channel": "gren", "r": 0, "g": 255, "b": 0, "gamma": 1}]}"#,
+    )
+    .unwrap()
+    .build();
+
+    let mut histogram = Histogram::new(2, 2, 1);
+    histogram.add_channel("red", vec![1.; 4]);
+    histogram.add_channel("green", vec![1.; 4]);
+    assert!(renderer.check_histogram(&histogram).is_err());
+    histogram.add_channel("gren", vec![1.; 4]);
+    assert!(renderer.check_histogram(&histogram).is_ok());
+}
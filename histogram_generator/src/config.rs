@@ -1,19 +1,36 @@
 use serde_derive::{Deserialize, Serialize};
 
+use fractatoe::fractals::buddhabrot::Buddhabrot;
+use fractatoe::fractals::burning_ship::{BurningShip, BurningShipJulia};
 use fractatoe::fractals::flame::FlameConf;
+use fractatoe::fractals::formula::FormulaConf;
 use fractatoe::fractals::histogram::HistogramBuilder;
 use fractatoe::fractals::julia::Julia;
 use fractatoe::fractals::mandelbrot::Mandelbrot;
+use fractatoe::fractals::multibrot::{Multibrot, MultibrotJulia};
+use fractatoe::fractals::newton::Newton;
+use fractatoe::fractals::tricorn::{Tricorn, TricornJulia};
 
 #[derive(Serialize, Deserialize)]
 pub enum FractalConf {
     Mandelbrot(Mandelbrot),
     Julia(Julia),
     Flame(FlameConf),
+    BurningShip(BurningShip),
+    BurningShipJulia(BurningShipJulia),
+    Tricorn(Tricorn),
+    TricornJulia(TricornJulia),
+    Multibrot(Multibrot),
+    MultibrotJulia(MultibrotJulia),
+    Formula(FormulaConf),
+    Newton(Newton),
+    Buddhabrot(Buddhabrot),
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct GenerationConf {
     pub histogram_conf: HistogramBuilder,
     pub fractal_conf: FractalConf,
+    /// Number of worker threads used for the generation, all the cores if absent
+    pub number_threads: Option<usize>,
 }
@@ -3,9 +3,11 @@
 #![feature(box_syntax)]
 
 use std::fs;
-use std::io::Write;
+use std::io::{BufWriter, Write};
 
 use argh::FromArgs;
+use fractatoe::fractals::flame::flam3;
+use fractatoe::fractals::histogram_file::{self, HistogramFileOptions, Precision};
 use fractatoe::fractals::{histogram::Histogram, HistogramGeneration};
 
 mod config;
@@ -19,37 +21,98 @@ struct Args {
     config_filename: String,
     #[argh(positional, description = "save the histogram to a file")]
     output_histogram: String,
+    #[argh(option, description = "also save the flame to a flam3 file")]
+    export_flame: Option<String>,
+    #[argh(switch, description = "save the histogram in the legacy json format")]
+    json: bool,
+    #[argh(switch, description = "save the values in single precision")]
+    single_precision: bool,
+    #[argh(switch, description = "do not compress the histogram")]
+    uncompressed: bool,
 }
 
 use config::{FractalConf, GenerationConf};
 
-fn get_histogram_from_gen_conf(gen_conf: GenerationConf) -> Histogram {
+fn get_histogram_from_gen_conf(gen_conf: GenerationConf) -> anyhow::Result<Histogram> {
     let histogram_conf = gen_conf.histogram_conf;
-    match gen_conf.fractal_conf {
-        FractalConf::Mandelbrot(generator) => generator.build_histogram(histogram_conf),
-        FractalConf::Julia(generator) => generator.build_histogram(histogram_conf),
-        FractalConf::Flame(generator) => generator.build().build_histogram(histogram_conf),
+    Ok(match gen_conf.fractal_conf {
+        FractalConf::Mandelbrot(generator) => generator.build()?.build_histogram(histogram_conf),
+        FractalConf::Julia(generator) => generator.build()?.build_histogram(histogram_conf),
+        FractalConf::Flame(generator) => generator.build()?.build_histogram(histogram_conf),
+        FractalConf::BurningShip(generator) => generator.build_histogram(histogram_conf),
+        FractalConf::BurningShipJulia(generator) => generator.build_histogram(histogram_conf),
+        FractalConf::Tricorn(generator) => generator.build_histogram(histogram_conf),
+        FractalConf::TricornJulia(generator) => generator.build_histogram(histogram_conf),
+        FractalConf::Multibrot(generator) => generator.build_histogram(histogram_conf),
+        FractalConf::MultibrotJulia(generator) => generator.build_histogram(histogram_conf),
+        FractalConf::Formula(generator) => generator.build()?.build_histogram(histogram_conf),
+        FractalConf::Newton(generator) => generator.build_histogram(histogram_conf),
+        FractalConf::Buddhabrot(generator) => generator.build_histogram(histogram_conf),
+    })
+}
+
+fn generate_histogram(gen_conf: GenerationConf) -> anyhow::Result<Histogram> {
+    let mut pool_builder = rayon::ThreadPoolBuilder::new();
+    if let Some(number_threads) = gen_conf.number_threads {
+        pool_builder = pool_builder.num_threads(number_threads);
     }
+    let pool = pool_builder.build()?;
+
+    pool.install(|| get_histogram_from_gen_conf(gen_conf))
 }
 
+/// Read a json configuration, or a flam3 file if the extension is .flame
 fn read_config_file(filename: &str) -> anyhow::Result<GenerationConf> {
+    if filename.ends_with(".flame") {
+        let (histogram_conf, flame_conf) = flam3::from_flam3(&fs::read_to_string(filename)?)?;
+        return Ok(GenerationConf {
+            histogram_conf,
+            fractal_conf: FractalConf::Flame(flame_conf),
+            number_threads: None,
+        });
+    }
     Ok(fs::read_to_string(filename).map(|x| serde_json::from_str(x.as_str()))??)
 }
 
+fn export_flame(gen_conf: &GenerationConf, filename: &str) -> anyhow::Result<()> {
+    match &gen_conf.fractal_conf {
+        FractalConf::Flame(flame_conf) => Ok(fs::write(
+            filename,
+            flam3::to_flam3(&gen_conf.histogram_conf, flame_conf)?,
+        )?),
+        _ => anyhow::bail!("Only flames can be exported to flam3"),
+    }
+}
+
 fn main() -> anyhow::Result<()> {
-    env_logger::Builder::from_env(env_logger::Env::default()).init();
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
     let args: Args = argh::from_env();
     let gen_conf = read_config_file(&args.config_filename)?;
+    if let Some(filename) = &args.export_flame {
+        export_flame(&gen_conf, filename)?;
+    }
 
-    let histogram = get_histogram_from_gen_conf(gen_conf);
+    let histogram = generate_histogram(gen_conf)?;
 
-    fs::File::create(args.output_histogram)?
-        .write_all(
+    let mut file = BufWriter::new(fs::File::create(args.output_histogram)?);
+    if args.json {
+        file.write_all(
             &serde_json::to_vec_pretty(&histogram)
                 .expect("Unable to convert the histogram in json file"),
-        )
-        .expect("Unable to write the histogram file");
+        )?;
+    } else {
+        let options = HistogramFileOptions {
+            precision: if args.single_precision {
+                Precision::Single
+            } else {
+                Precision::Double
+            },
+            compression: !args.uncompressed,
+        };
+        histogram_file::write_histogram(&histogram, options, &mut file)?;
+    }
+    file.flush()?;
 
     Ok(())
 }
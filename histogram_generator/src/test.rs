@@ -1,4 +1,6 @@
 use super::*;
+use fractatoe::fractals::escape_time::{iterate_until_escape, Escape, INTERIOR_CHANNEL};
+use num::complex::Complex;
 
 #[test]
 fn test_julia_conf() {
@@ -14,3 +16,125 @@ fn test_mandelbrot_conf() {
 fn test_sierpinsky_conf() {
     read_config_file("../samples/sierpinsky.json").unwrap();
 }
+
+#[test]
+fn test_parallel_generation_is_deterministic() {
+    let mut gen_conf = read_config_file("../samples/julia.json").unwrap();
+    gen_conf.number_threads = Some(1);
+    let serial = generate_histogram(gen_conf).unwrap();
+
+    let mut gen_conf = read_config_file("../samples/julia.json").unwrap();
+    gen_conf.number_threads = Some(4);
+    let parallel = generate_histogram(gen_conf).unwrap();
+
+    assert!(serial == parallel);
+
+    // Per-pixel iteration of the sample Julia set, as before the parallel generation
+    let builder = read_config_file("../samples/julia.json")
+        .unwrap()
+        .histogram_conf;
+    let c = Complex::new(-0.8, 0.2);
+    let mut reference = Histogram::new(builder.width_px, builder.height_px, builder.resolution_px);
+    let width = builder.width_px * builder.resolution_px;
+    let mut interior = vec![0.; width * builder.height_px * builder.resolution_px];
+    for ((i, j), (x, y)) in builder.iter_over_pixels() {
+        let escape = iterate_until_escape(Complex::new(x, y), 0, |z| z * z + c, 50., 200, None);
+        match escape {
+            Escape::Escaped(iteration) => reference.set_cell(i, j, (iteration, 0.)),
+            Escape::Interior => interior[i + width * j] = 1.,
+        }
+    }
+    reference.add_channel(INTERIOR_CHANNEL, interior);
+
+    assert!(reference == serial);
+    assert!(reference == parallel);
+}
+
+#[test]
+fn test_flame_generation_is_deterministic() {
+    let histograms: Vec<Histogram> = (0..2)
+        .map(|_| {
+            let mut gen_conf = read_config_file("../samples/sierpinsky.json").unwrap();
+            gen_conf.number_threads = Some(3);
+            generate_histogram(gen_conf).unwrap()
+        })
+        .collect();
+
+    assert!(histograms[0] == histograms[1]);
+}
+
+#[test]
+fn test_mandelbrot_smooth_conf() {
+    read_config_file("../samples/mandelbrot_smooth.json").unwrap();
+}
+
+#[test]
+fn test_burning_ship_conf() {
+    read_config_file("../samples/burning_ship.json").unwrap();
+}
+
+#[test]
+fn test_tricorn_julia_conf() {
+    read_config_file("../samples/tricorn_julia.json").unwrap();
+}
+
+#[test]
+fn test_multibrot_conf() {
+    read_config_file("../samples/multibrot.json").unwrap();
+    read_config_file("../samples/multibrot_negative.json").unwrap();
+}
+
+#[test]
+fn test_formula_conf() {
+    let gen_conf = read_config_file("../samples/formula.json").unwrap();
+    match gen_conf.fractal_conf {
+        FractalConf::Formula(formula) => {
+            formula.build().unwrap();
+        }
+        _ => panic!("Expected a formula configuration"),
+    }
+}
+
+#[test]
+fn test_newton_conf() {
+    read_config_file("../samples/newton.json").unwrap();
+    read_config_file("../samples/nova.json").unwrap();
+}
+
+#[test]
+fn test_buddhabrot_conf() {
+    read_config_file("../samples/nebulabrot.json").unwrap();
+    read_config_file("../samples/anti_buddhabrot.json").unwrap();
+}
+
+#[test]
+fn test_mandelbrot_deep_zoom_conf() {
+    read_config_file("../samples/mandelbrot_deep_zoom.json").unwrap();
+}
+
+#[test]
+fn test_mandelbrot_distance_conf() {
+    read_config_file("../samples/mandelbrot_distance.json").unwrap();
+}
+
+#[test]
+fn test_mandelbrot_orbit_trap_conf() {
+    read_config_file("../samples/mandelbrot_orbit_trap.json").unwrap();
+}
+
+#[test]
+fn test_flame_xforms_conf() {
+    read_config_file("../samples/sierpinsky_colored.json").unwrap();
+}
+
+#[test]
+fn test_flame_variations_conf() {
+    read_config_file("../samples/flame_variations.json").unwrap();
+}
+
+#[test]
+fn test_flam3_conf() {
+    let gen_conf = read_config_file("../samples/apophysis.flame").unwrap();
+    assert_eq!(gen_conf.histogram_conf.width_px, 800);
+    assert_eq!(gen_conf.histogram_conf.resolution_px, 2);
+}
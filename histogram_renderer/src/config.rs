@@ -1,7 +1,11 @@
 use serde_derive::{Deserialize, Serialize};
 
+pub use fractatoe::rendering::basin_rendering::BasinRendererConf;
+pub use fractatoe::rendering::channel_rendering::ChannelRendererConf;
+pub use fractatoe::rendering::distance_rendering::DistanceRendererConf;
 pub use fractatoe::rendering::flame_rendering::FlameRendererConf;
 pub use fractatoe::rendering::gaussian_rendering::GaussianRendererConf;
+pub use fractatoe::rendering::log_density_rendering::LogDensityRendererConf;
 pub use fractatoe::rendering::mandelbrot_rendering::GreyscaleRendererConf;
 pub use fractatoe::rendering::mandelbrot_rendering::MandelbrotRendererConf;
 
@@ -11,4 +15,8 @@ pub enum RenderingConf {
     FlameRendering(FlameRendererConf),
     GaussianRendering(GaussianRendererConf),
     GreyscaleRendering(GreyscaleRendererConf),
+    BasinRendering(BasinRendererConf),
+    ChannelRendering(ChannelRendererConf),
+    DistanceRendering(DistanceRendererConf),
+    LogDensityRendering(LogDensityRendererConf),
 }
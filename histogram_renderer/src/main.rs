@@ -1,5 +1,6 @@
 use argh::FromArgs;
 use std::fs;
+use std::io::BufReader;
 
 #[derive(FromArgs)]
 /// Arguments
@@ -15,6 +16,7 @@ struct Args {
 }
 
 use fractatoe::fractals::histogram::Histogram;
+use fractatoe::fractals::histogram_file;
 use fractatoe::image::Image;
 use fractatoe::rendering::HistogramRendering;
 
@@ -26,12 +28,26 @@ mod window;
 
 use config::RenderingConf;
 
-fn render_image(rendering_conf: RenderingConf, histogram: Histogram) -> Image {
+/// Render the histogram, once checked that it suits the renderer
+fn render<R: HistogramRendering>(renderer: R, histogram: Histogram) -> anyhow::Result<Image> {
+    renderer
+        .check_histogram(&histogram)
+        .map_err(anyhow::Error::msg)?;
+    Ok(renderer.render_image(histogram))
+}
+
+fn render_image(rendering_conf: RenderingConf, histogram: Histogram) -> anyhow::Result<Image> {
     match rendering_conf {
-        RenderingConf::MandelbrotRendering(conf) => conf.build().render_image(histogram),
-        RenderingConf::FlameRendering(conf) => conf.build().render_image(histogram),
-        RenderingConf::GaussianRendering(conf) => conf.build().render_image(histogram),
-        RenderingConf::GreyscaleRendering(conf) => conf.build().render_image(histogram),
+        RenderingConf::MandelbrotRendering(conf) => render(conf.build(), histogram),
+        RenderingConf::FlameRendering(conf) => render(conf.build(), histogram),
+        RenderingConf::GaussianRendering(conf) => render(conf.build(), histogram),
+        RenderingConf::GreyscaleRendering(conf) => render(conf.build(), histogram),
+        RenderingConf::BasinRendering(conf) => {
+            render(conf.build().map_err(anyhow::Error::msg)?, histogram)
+        }
+        RenderingConf::ChannelRendering(conf) => render(conf.build(), histogram),
+        RenderingConf::DistanceRendering(conf) => render(conf.build(), histogram),
+        RenderingConf::LogDensityRendering(conf) => render(conf.build(), histogram),
     }
 }
 
@@ -47,10 +63,11 @@ fn main() -> anyhow::Result<()> {
     let rendering_conf = fs::read_to_string(args.config_filename)
         .map(|x| serde_json::from_str::<RenderingConf>(x.as_str()))??;
 
-    let histogram = fs::read_to_string(args.histogram_filename)
-        .map(|x| serde_json::from_str::<Histogram>(x.as_str()))??;
+    // Binary or legacy json histogram
+    let histogram =
+        histogram_file::read_histogram(BufReader::new(fs::File::open(args.histogram_filename)?))?;
 
-    let image = render_image(rendering_conf, histogram);
+    let image = render_image(rendering_conf, histogram)?;
 
     if let Some(image_path) = args.output_image {
         png_save::save_image(&image, image_path)?;